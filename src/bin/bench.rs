@@ -0,0 +1,101 @@
+extern crate clap;
+extern crate gomoku;
+extern crate time;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::i32;
+use std::i64;
+use std::io::Read;
+use std::path::Path;
+use std::process;
+use time::PreciseTime;
+use gomoku::board::{Board, Move, Square};
+use gomoku::eval::{PatternEvaluator, PERSONALITIES};
+use gomoku::minimax::{minimax, SearchContext, SearchStats};
+use gomoku::tuner;
+
+const DEFAULT_DEPTH: &'static str = "4";
+
+// Openings searched when no positions file is given
+const OPENINGS: [&'static [(usize, usize)]; 6] = [
+    &[(9, 9), (10, 10)],
+    &[(9, 9), (9, 10), (10, 9), (8, 8)],
+    &[(9, 9), (10, 10), (9, 10), (10, 11), (8, 8), (11, 9), (7, 9), (8, 11)],
+    &[(9, 9), (10, 8), (10, 10), (11, 11), (8, 8), (7, 7), (8, 10)],
+    &[(3, 3), (15, 15), (4, 4), (14, 14), (5, 5), (9, 9)],
+    &[(9, 9), (9, 10), (9, 8), (9, 11), (10, 9), (8, 9), (10, 10), (11, 11), (8, 10)],
+];
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn opening(moves: &[(usize, usize)]) -> (Board, Square) {
+    let mut board = Board::new();
+    let mut color = Square::Black;
+    for &pos in moves {
+        match board.play_at(Some(pos), &color, PreciseTime::now(), true) {
+            Move::Legal(next, _, _, _) => board = next,
+            _ => fail(format!("Illegal opening move {}", Board::notation(pos))),
+        }
+        color = color.opposite();
+    }
+    (board, color)
+}
+
+fn main() {
+    let options = clap::App::new("Gomoku bench")
+        .version("0.1")
+        .about("Searches a set of positions to a fixed depth and prints the search statistics, to compare move orderings.")
+        .arg(clap::Arg::with_name("positions")
+             .help("File of positions in the format read by gomoku-tune, built-in openings by default."))
+        .arg(clap::Arg::with_name("depth")
+             .help("Depth searched, iteratively from 1.")
+             .takes_value(true)
+             .short("d")
+             .long("depth"))
+        .arg(clap::Arg::with_name("personality")
+             .help("Evaluation weights used by the search.")
+             .takes_value(true)
+             .short("p")
+             .long("personality")
+             .possible_values(&PERSONALITIES.iter().map(|&(name, _)| name).collect::<Vec<_>>()))
+        .get_matches();
+
+    let value = options.value_of("depth").unwrap_or(DEFAULT_DEPTH);
+    let depth = value.parse::<usize>().unwrap_or_else(|_| fail(format!("Invalid value `{}` for --depth", value)));
+    let evaluator = PatternEvaluator::personality(options.value_of("personality").unwrap_or("default")).unwrap();
+    let positions = match options.value_of("positions") {
+        Some(path) => {
+            let path = Path::new(path);
+            let mut data = String::new();
+            File::open(path)
+                .and_then(|mut file| file.read_to_string(&mut data))
+                .unwrap_or_else(|e| fail(format!("{}: {}", path.display(), e)));
+            tuner::parse_samples(&data)
+                .unwrap_or_else(|e| fail(format!("{}: {}", path.display(), e)))
+                .into_iter()
+                .map(|sample| (sample.board, sample.to_move))
+                .collect()
+        },
+        None => OPENINGS.iter().map(|moves| opening(moves)).collect::<Vec<_>>(),
+    };
+
+    let start = PreciseTime::now();
+    let mut total = SearchStats::new();
+    for &(ref board, ref color) in &positions {
+        let mut ctx = SearchContext::new(PreciseTime::now(), i64::MAX, HashMap::new());
+        for d in 1..depth + 1 {
+            minimax(board, d, i32::MIN, i32::MAX, true, None, color, &mut ctx, &evaluator);
+        }
+        total.nodes += ctx.stats.nodes;
+        total.tt_hits += ctx.stats.tt_hits;
+        total.cutoffs += ctx.stats.cutoffs;
+        total.first_move_cutoffs += ctx.stats.first_move_cutoffs;
+        total.quiescence_nodes += ctx.stats.quiescence_nodes;
+    }
+    println!("{} positions to depth {} in {}", positions.len(), depth, start.to(PreciseTime::now()));
+    println!("{}", total);
+}
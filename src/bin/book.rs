@@ -0,0 +1,112 @@
+extern crate clap;
+extern crate gomoku;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process;
+use gomoku::book::{self, Book};
+use gomoku::eval::{PatternEvaluator, PERSONALITIES};
+
+const DEFAULT_PLIES: &'static str = "4";
+const DEFAULT_WIDTH: &'static str = "3";
+const DEFAULT_DEPTH: &'static str = "4";
+const DEFAULT_TIME: &'static str = "10000";
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let options = clap::App::new("Gomoku opening book")
+        .version("0.1")
+        .about("Builds an opening book from game records or from deep searches of the first moves.")
+        .arg(clap::Arg::with_name("output")
+             .help("File to write the book to.")
+             .required(true))
+        .arg(clap::Arg::with_name("book")
+             .help("Book to extend, the output starts empty by default.")
+             .takes_value(true)
+             .short("b")
+             .long("book"))
+        .arg(clap::Arg::with_name("games")
+             .help("File of games, one per line: moves `x,y` from 0 to 18 separated by spaces, Black first, optionally followed by the winner, B or W.")
+             .takes_value(true)
+             .short("g")
+             .long("games"))
+        .arg(clap::Arg::with_name("search")
+             .help("Searches the best moves of the positions reached from the empty board.")
+             .short("s")
+             .long("search"))
+        .arg(clap::Arg::with_name("plies")
+             .help("Number of moves of each game, or of the searched openings, added to the book.")
+             .takes_value(true)
+             .short("n")
+             .long("plies"))
+        .arg(clap::Arg::with_name("width")
+             .help("Number of moves kept in each searched position.")
+             .takes_value(true)
+             .long("width"))
+        .arg(clap::Arg::with_name("depth")
+             .help("Depth of the searches.")
+             .takes_value(true)
+             .short("d")
+             .long("depth"))
+        .arg(clap::Arg::with_name("time")
+             .help("Time limit of each search, in milliseconds.")
+             .takes_value(true)
+             .short("t")
+             .long("time"))
+        .arg(clap::Arg::with_name("personality")
+             .help("Evaluation weights of the searches.")
+             .takes_value(true)
+             .short("p")
+             .long("personality")
+             .possible_values(&PERSONALITIES.iter().map(|&(name, _)| name).collect::<Vec<_>>())
+             .conflicts_with("weights"))
+        .arg(clap::Arg::with_name("weights")
+             .help("File containing the evaluation weights of the searches.")
+             .takes_value(true)
+             .short("w")
+             .long("weights"))
+        .get_matches();
+
+    let number = |name: &str, default: &str| {
+        let value = options.value_of(name).unwrap_or(default);
+        value.parse::<usize>().unwrap_or_else(|_| fail(format!("Invalid value `{}` for --{}", value, name)))
+    };
+    let plies = number("plies", DEFAULT_PLIES);
+
+    let mut book = match options.value_of("book") {
+        Some(path) => Book::load(Path::new(path)).unwrap_or_else(|e| fail(format!("Cannot load the book: {}", e))),
+        None => Book::new(),
+    };
+
+    if let Some(path) = options.value_of("games") {
+        let mut data = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut data))
+            .unwrap_or_else(|e| fail(format!("{}: {}", path, e)));
+        for (n, line) in data.lines().enumerate().filter(|&(_, line)| !line.trim().is_empty() && !line.starts_with('#')) {
+            book::parse_game(line)
+                .and_then(|(moves, winner)| book.add_game(&moves, winner, plies))
+                .unwrap_or_else(|e| fail(format!("{}: line {}: {}", path, n + 1, e)));
+        }
+    }
+
+    if options.is_present("search") {
+        let evaluator = match options.value_of("weights") {
+            Some(path) => PatternEvaluator::load(Path::new(path)).unwrap_or_else(|e| fail(format!("Cannot load the evaluation weights: {}", e))),
+            None => PatternEvaluator::personality(options.value_of("personality").unwrap_or("default")).unwrap(),
+        };
+        book.add_searches(plies, number("width", DEFAULT_WIDTH), number("depth", DEFAULT_DEPTH),
+                          number("time", DEFAULT_TIME) as i64, &evaluator);
+    }
+
+    let output = options.value_of("output").unwrap();
+    File::create(output)
+        .and_then(|mut file| file.write_all(book.to_text().as_bytes()))
+        .unwrap_or_else(|e| fail(format!("{}: {}", output, e)));
+    eprintln!("{} positions written to {}", book.len(), output);
+}
@@ -0,0 +1,102 @@
+extern crate clap;
+extern crate gomoku;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process;
+use gomoku::eval::{PatternEvaluator, PERSONALITIES};
+use gomoku::tuner;
+
+const DEFAULT_DEPTH: &'static str = "2";
+const DEFAULT_ITERATIONS: &'static str = "100";
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let options = clap::App::new("Gomoku tuner")
+        .version("0.1")
+        .about("Tunes the evaluation weights of the AI on positions labeled with the result of their game.")
+        .arg(clap::Arg::with_name("positions")
+             .help("File of positions, one per line: 361 squares (B, W or _) row by row, the player to move, black and white captures, and the result for the player to move (1, 0.5 or 0).")
+             .required(true))
+        .arg(clap::Arg::with_name("generate")
+             .help("Plays this number of games of the AI against itself and writes their positions to the positions file first.")
+             .takes_value(true)
+             .short("g")
+             .long("generate"))
+        .arg(clap::Arg::with_name("depth")
+             .help("Search depth of the games played with --generate.")
+             .takes_value(true)
+             .short("d")
+             .long("depth"))
+        .arg(clap::Arg::with_name("iterations")
+             .help("Maximum number of passes over the weights.")
+             .takes_value(true)
+             .short("i")
+             .long("iterations"))
+        .arg(clap::Arg::with_name("personality")
+             .help("Evaluation weights to start from.")
+             .takes_value(true)
+             .short("p")
+             .long("personality")
+             .possible_values(&PERSONALITIES.iter().map(|&(name, _)| name).collect::<Vec<_>>())
+             .conflicts_with("weights"))
+        .arg(clap::Arg::with_name("weights")
+             .help("File containing the evaluation weights to start from.")
+             .takes_value(true)
+             .short("w")
+             .long("weights"))
+        .arg(clap::Arg::with_name("output")
+             .help("File to write the tuned weights to, standard output by default.")
+             .takes_value(true)
+             .short("o")
+             .long("output"))
+        .get_matches();
+
+    let number = |name: &str, default: &str| {
+        let value = options.value_of(name).unwrap_or(default);
+        value.parse::<usize>().unwrap_or_else(|_| fail(format!("Invalid value `{}` for --{}", value, name)))
+    };
+    let evaluator = match options.value_of("weights") {
+        Some(path) => PatternEvaluator::load(Path::new(path)).unwrap_or_else(|e| fail(format!("Cannot load the evaluation weights: {}", e))),
+        None => PatternEvaluator::personality(options.value_of("personality").unwrap_or("default")).unwrap(),
+    };
+    let path = Path::new(options.value_of("positions").unwrap());
+
+    if options.is_present("generate") {
+        let samples = tuner::self_play(number("generate", "0"), number("depth", DEFAULT_DEPTH), &evaluator);
+        let data = samples.iter().map(|sample| sample.to_line() + "\n").collect::<String>();
+        File::create(path)
+            .and_then(|mut file| file.write_all(data.as_bytes()))
+            .unwrap_or_else(|e| fail(format!("{}: {}", path.display(), e)));
+        eprintln!("{} positions written to {}", samples.len(), path.display());
+    }
+
+    let mut data = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut data))
+        .unwrap_or_else(|e| fail(format!("{}: {}", path.display(), e)));
+    let samples = tuner::parse_samples(&data).unwrap_or_else(|e| fail(format!("{}: {}", path.display(), e)));
+    let count = samples.len();
+    let samples = tuner::dedup(samples);
+    eprintln!("{} positions, {} once symmetric duplicates are merged", count, samples.len());
+    if samples.is_empty() {
+        fail(format!("{}: no positions", path.display()));
+    }
+
+    let progress = |iteration: usize, error: f64, step: i32| eprintln!("Iteration {}: error {:.6}, step {}", iteration, error, step);
+    let tuned = tuner::tune(&samples, &evaluator, number("iterations", DEFAULT_ITERATIONS), &progress);
+    let k = tuner::fit_scale(&samples, &evaluator);
+    eprintln!("Error on {} positions: {:.6} before, {:.6} after",
+             samples.len(), tuner::error(&samples, &evaluator, k), tuner::error(&samples, &tuned, k));
+    match options.value_of("output") {
+        Some(output) => File::create(output)
+            .and_then(|mut file| file.write_all(tuned.to_config().as_bytes()))
+            .unwrap_or_else(|e| fail(format!("{}: {}", output, e))),
+        None => print!("{}", tuned.to_config()),
+    }
+}
@@ -0,0 +1,270 @@
+use board::square::Square;
+
+use std::cmp;
+
+pub const SIZE: usize = 19;
+
+// Line families in the order produced by `Board::explode`:
+// fixed x, fixed y, x - y constant, x + y constant.
+pub const DIRECTIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct BitBoard
+{
+    black: [u64; 6],
+    white: [u64; 6],
+}
+
+pub struct Line
+{
+    pub dir: usize,
+    pub start: (usize, usize),
+    pub len: usize,
+    pub mask: [u64; 6],
+    bits: Vec<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LineBits
+{
+    pub black: u32,
+    pub white: u32,
+    pub len: usize,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Pattern
+{
+    pub len: usize,
+    black: u32,
+    white: u32,
+    empty: u32,
+}
+
+lazy_static! {
+    pub static ref LINES: Vec<Line> = Line::all();
+}
+
+fn bit(pos: (usize, usize)) -> usize {
+    pos.0 * SIZE + pos.1
+}
+
+impl Line
+{
+    fn new(dir: usize, start: (usize, usize), len: usize) -> Line {
+        let (dx, dy) = DIRECTIONS[dir];
+        let bits = (0..len as i32)
+            .map(|i| bit(((start.0 as i32 + i * dx) as usize, (start.1 as i32 + i * dy) as usize)))
+            .collect::<Vec<usize>>();
+        let mut mask = [0u64; 6];
+        for &b in &bits {
+            mask[b / 64] |= 1 << (b % 64);
+        }
+        Line { dir: dir, start: start, len: len, mask: mask, bits: bits }
+    }
+
+    fn all() -> Vec<Line> {
+        let mut lines = Vec::new();
+        lines.extend((0..19).map(|i| Line::new(0, (i, 0), 19)));
+        lines.extend((0..19).map(|i| Line::new(1, (0, i), 19)));
+        lines.extend((0..37).map(|i: i32| Line::new(2,
+            (cmp::max(0, i - 18) as usize, cmp::max(0, 18 - i) as usize),
+            (19 - (18 - i).abs()) as usize)));
+        lines.extend((0..37).map(|i: i32| Line::new(3,
+            (cmp::max(0, i - 18) as usize, cmp::min(18, i) as usize),
+            (19 - (18 - i).abs()) as usize)));
+        lines
+    }
+
+    // Index in `LINES` of the line going through `pos` in direction `dir`,
+    // along with the offset of `pos` on that line.
+    pub fn through(pos: (usize, usize), dir: usize) -> (usize, usize) {
+        let (x, y) = pos;
+        match dir {
+            0 => (x, y),
+            1 => (19 + y, x),
+            2 => (38 + 18 + x - y, cmp::min(x, y)),
+            _ => (75 + x + y, x - (x + y).saturating_sub(18)),
+        }
+    }
+
+    pub fn pos(&self, i: usize) -> (usize, usize) {
+        let (dx, dy) = DIRECTIONS[self.dir];
+        ((self.start.0 as i32 + i as i32 * dx) as usize,
+         (self.start.1 as i32 + i as i32 * dy) as usize)
+    }
+}
+
+impl BitBoard
+{
+    pub fn new() -> BitBoard {
+        BitBoard { black: [0; 6], white: [0; 6] }
+    }
+
+    pub fn get(&self, pos: (usize, usize)) -> Square {
+        let b = bit(pos);
+        if self.black[b / 64] & (1 << (b % 64)) != 0 {
+            Square::Black
+        }
+        else if self.white[b / 64] & (1 << (b % 64)) != 0 {
+            Square::White
+        }
+        else {
+            Square::Empty
+        }
+    }
+
+    pub fn set(&mut self, pos: (usize, usize), color: &Square) {
+        let b = bit(pos);
+        self.black[b / 64] &= !(1 << (b % 64));
+        self.white[b / 64] &= !(1 << (b % 64));
+        match *color {
+            Square::Black => self.black[b / 64] |= 1 << (b % 64),
+            Square::White => self.white[b / 64] |= 1 << (b % 64),
+            Square::Empty => (),
+        }
+    }
+
+    pub fn count(&self, color: &Square) -> u32 {
+        match *color {
+            Square::Black => self.black.iter().map(|w| w.count_ones()).sum(),
+            Square::White => self.white.iter().map(|w| w.count_ones()).sum(),
+            Square::Empty => 361 - self.count(&Square::Black) - self.count(&Square::White),
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.count(&Square::Empty) == 0
+    }
+
+    pub fn is_empty_line(&self, line: &Line) -> bool {
+        (0..6).all(|i| (self.black[i] | self.white[i]) & line.mask[i] == 0)
+    }
+
+    pub fn line(&self, line: &Line) -> LineBits {
+        let mut bits = LineBits { black: 0, white: 0, len: line.len };
+        if self.is_empty_line(line) {
+            return bits;
+        }
+        for (i, &b) in line.bits.iter().enumerate() {
+            if self.black[b / 64] & (1 << (b % 64)) != 0 {
+                bits.black |= 1 << i;
+            }
+            else if self.white[b / 64] & (1 << (b % 64)) != 0 {
+                bits.white |= 1 << i;
+            }
+        }
+        bits
+    }
+}
+
+impl LineBits
+{
+    pub fn empty(&self) -> u32 {
+        !(self.black | self.white) & ((1 << self.len) - 1)
+    }
+
+    pub fn own(&self, color: &Square) -> u32 {
+        match *color {
+            Square::Black => self.black,
+            Square::White => self.white,
+            Square::Empty => self.empty(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.black | self.white == 0
+    }
+
+    // Copy of the line with the square at offset `i` replaced by `color`.
+    pub fn with(&self, i: usize, color: &Square) -> LineBits {
+        let mut line = *self;
+        line.black &= !(1 << i);
+        line.white &= !(1 << i);
+        match *color {
+            Square::Black => line.black |= 1 << i,
+            Square::White => line.white |= 1 << i,
+            Square::Empty => (),
+        }
+        line
+    }
+
+    // Squares `lo..hi` of the line, clipped to its bounds.
+    pub fn slice(&self, lo: i32, hi: i32) -> LineBits {
+        let lo = cmp::max(0, lo) as usize;
+        let hi = cmp::min(self.len as i32, hi) as usize;
+        if hi <= lo {
+            return LineBits { black: 0, white: 0, len: 0 };
+        }
+        let mask = (1 << (hi - lo)) - 1;
+        LineBits { black: (self.black >> lo) & mask, white: (self.white >> lo) & mask, len: hi - lo }
+    }
+
+    pub fn matches_at(&self, pattern: &Pattern, offset: i32) -> bool {
+        if offset < 0 || offset as usize + pattern.len > self.len {
+            return false;
+        }
+        let mask = (1 << pattern.len) - 1;
+        let offset = offset as usize;
+        (self.black >> offset) & mask == pattern.black
+            && (self.white >> offset) & mask == pattern.white
+            && (self.empty() >> offset) & mask == pattern.empty
+    }
+
+    pub fn find(&self, pattern: &Pattern) -> Option<usize> {
+        if pattern.len > self.len {
+            return None;
+        }
+        (0..self.len - pattern.len + 1).find(|&i| self.matches_at(pattern, i as i32))
+    }
+
+    pub fn find_all(&self, pattern: &Pattern) -> Vec<usize> {
+        if pattern.len > self.len {
+            return vec![];
+        }
+        (0..self.len - pattern.len + 1).filter(|&i| self.matches_at(pattern, i as i32)).collect()
+    }
+
+    // Length of the run of `color` stones going through offset `i`.
+    pub fn run_through(&self, color: &Square, i: usize) -> usize {
+        let own = self.own(color);
+        if own & (1 << i) == 0 {
+            return 0;
+        }
+        let after = (!(own >> i)).trailing_zeros() as usize;
+        let before = if i == 0 { 0 } else { (!(own << (32 - i))).leading_zeros() as usize };
+        before + after
+    }
+
+    pub fn to_string(&self) -> String {
+        (0..self.len).map(|i| if self.black & (1 << i) != 0 { 'B' }
+                          else if self.white & (1 << i) != 0 { 'W' }
+                          else { '-' })
+            .collect::<String>()
+    }
+}
+
+impl<'a> From<&'a str> for Pattern
+{
+    // 'B' and 'W' are stones, '-' or ' ' an empty square.
+    fn from(s: &'a str) -> Self {
+        s.chars().enumerate().fold(Pattern { len: s.len(), black: 0, white: 0, empty: 0 }, |mut p, (i, c)| {
+            match c {
+                'B' => p.black |= 1 << i,
+                'W' => p.white |= 1 << i,
+                _ => p.empty |= 1 << i,
+            }
+            p
+        })
+    }
+}
+
+impl Pattern
+{
+    // Builds a pattern from a template where 'x' stands for `color` and 'y'
+    // for its opponent.
+    pub fn from_template(template: &str, color: &Square) -> Pattern {
+        Pattern::from(template.replace("x", color.to_str())
+                      .replace("y", color.opposite().to_str()).as_str())
+    }
+}
@@ -1,10 +1,11 @@
 use board::square::Square;
+use board::bitboard::{BitBoard, LINES};
+use board::patterns::{PatternCache, Weights};
+use board::rules::Rules;
 
 use std::fmt;
-use std::cmp;
 use board::itertools::Itertools;
-use board::rand::Rng;
-use board::rand;
+use board::rand::{Rng, SeedableRng, XorShiftRng};
 use board::time::{PreciseTime, Duration};
 
 #[derive(Clone)]
@@ -16,6 +17,9 @@ pub struct Board
     pub w_capture: usize,
     pub game_state: BoardState,
     pub hash: u64,
+    pub bits: BitBoard,
+    pub patterns: PatternCache,
+    pub rules: Rules,
 }
 
 #[derive(Clone, Debug)]
@@ -44,7 +48,20 @@ pub struct Right {
     pub fun: Box<Fn(usize) -> (usize, usize)>,
 }
 
-static mut ZOBRIST_ARRAY: [[u64; 361]; 2] = [[0; 361]; 2];
+lazy_static! {
+    // Drawn from a fixed seed, so that hashes stay the same from one run to
+    // the other and can be saved, as in the opening book.
+    static ref ZOBRIST_KEYS: Vec<[u64; 361]> = {
+        let mut rng = XorShiftRng::from_seed([0x476f_6d6f, 0x6b75_2031, 0x3933_3731, 0x9e37_79b9]);
+        (0..2).map(|_| {
+            let mut keys = [0; 361];
+            for key in keys.iter_mut() {
+                *key = rng.gen::<u64>();
+            }
+            keys
+        }).collect()
+    };
+}
 
 impl fmt::Display for Board
 {
@@ -91,7 +108,16 @@ impl<'a> From<&'a str> for Board
             w_capture: 0,
             hash: 0,
             game_state: BoardState::InProgress,
+            bits: BitBoard::new(),
+            patterns: PatternCache::new(),
+            rules: Rules::standard(),
         };
+        for i in 0..board.state.len() {
+            for j in 0..board.state[i].len() {
+                board.bits.set((i, j), &board.state[i][j]);
+            }
+        }
+        board.patterns = PatternCache::from_bits(&board.bits);
         board.generate_hash();
         board
     }
@@ -99,71 +125,55 @@ impl<'a> From<&'a str> for Board
 
 impl Board {
     pub fn new() -> Board {
+        Board::with_rules(Rules::standard())
+    }
+
+    pub fn with_rules(rules: Rules) -> Board {
         Board {
             state : vec![vec![Square::Empty; 19]; 19],
             b_capture : 0,
             w_capture : 0,
             game_state: BoardState::InProgress,
             hash : 0,
+            bits: BitBoard::new(),
+            patterns: PatternCache::new(),
+            rules: rules,
         }
     }
 
     pub fn explode(&self) -> Vec<Right> {
-        let mut t: Vec<Right> = Vec::new();
-        {
-            let mut vert = (0..19).map(|i| Right { data: (0..19)
-                                       .map(|j| self.state[i][j].to_char())
-                                       .collect::<String>(),
-                                       fun: Box::new(move |v| (i, v as usize)) }
-                                       ).collect::<Vec<Right>>();
-            let mut hor = (0..19).map(|i| Right { data: (0..19)
-                                        .map(|j| self.state[j][i].to_char())
-                                        .collect::<String>(),
-                                        fun: Box::new(move |v| (v as usize, i)) } 
-                                        ).collect::<Vec<Right>>();
-            let mut diagup = (0..37)
-                .map(|i| Right { data: (0..19 - (19 - (i as i32 + 1)).abs())
-                    .map(|j| self.state[(cmp::max(0, i - 18) + j) as usize]
-                         [(cmp::max(0, 18 - i) + j) as usize].to_char())
-                    .collect::<String>(), 
-                    fun: Box::new(move |v| (cmp::max(0, i as i32 - 18) as usize + v, cmp::max(0, 18 - i as i32) as usize + v))
-                }).collect::<Vec<Right>>();
-            let mut diagdown = (0..37)
-                .map(|i| Right { data: (0..19 - (19 - (i as i32 + 1)).abs())
-                    .map(|j| self.state[(cmp::max(0, i - 18) + j) as usize]
-                         [(cmp::min(18, i) - j) as usize].to_char())
-                    .collect::<String>(),
-                    fun: Box::new(move |v| (cmp::max(0, i as i32 - 18) as usize + v, cmp::min(18, i as i32) as usize - v))
-                }).collect::<Vec<Right>>();
-            t.append(&mut vert);
-            t.append(&mut hor);
-            t.append(&mut diagup);
-            t.append(&mut diagdown);
-        }
-        t
+        LINES.iter().map(|line| Right { data: self.bits.line(line).to_string(),
+                                        fun: Box::new(move |v| line.pos(v)) })
+            .collect::<Vec<Right>>()
     }
 
-    pub fn init_zobrist_array() {
-        unsafe {
-            let mut rng = rand::thread_rng();
-            for i in 0..2 {
-                for j in 0..361 {
-                    ZOBRIST_ARRAY[i][j] = rng.gen::<u64>();
-                }
-            }
-        }
+    pub fn set(&mut self, pos: (usize, usize), color: &Square) {
+        self.state[pos.0][pos.1] = color.clone();
+        self.bits.set(pos, color);
+        self.patterns.update(&self.bits, pos);
     }
 
-    pub fn add_move(&mut self, pos: (usize, usize), color: &Square) 
+    // Standard notation of `pos`: the column as a letter from the left, I
+    // being skipped, then the row numbered from the bottom.
+    pub fn notation(pos: (usize, usize)) -> String
+    {
+        let columns = "ABCDEFGHJKLMNOPQRST";
+        format!("{}{}", &columns[pos.0..pos.0 + 1], 19 - pos.1)
+    }
+
+    pub fn zobrist_key(pos: (usize, usize), color: &Square) -> u64
     {
         let (x, y) = pos;
-        unsafe {
-            self.hash ^= ZOBRIST_ARRAY[match *color {
-                Square::Black => 0,
-                Square::White => 1,
-                _ => 0,
-            }][x * 19 + y];
-        };
+        ZOBRIST_KEYS[match *color {
+            Square::Black => 0,
+            Square::White => 1,
+            _ => 0,
+        }][x * 19 + y]
+    }
+
+    pub fn add_move(&mut self, pos: (usize, usize), color: &Square) 
+    {
+        self.hash ^= Board::zobrist_key(pos, color);
     }
 
     pub fn generate_hash(&mut self)
@@ -219,11 +229,13 @@ impl Board {
                     Move::Illegal
                 }
                 else {
-                    clone.state[x][y] = color.clone();
-                    if !clone.check_moveintocapture(color, (x, y)) {
-                        if !clone.check_free_threes(x as i32, y as i32, color)
+                    clone.set((x, y), color);
+                    if !self.rules.captures || !clone.check_moveintocapture(color, (x, y)) {
+                        if !self.rules.double_threes || !clone.check_free_threes(x as i32, y as i32, color)
                         {
-                            clone = clone.check_capture(color, (x, y));
+                            if self.rules.captures {
+                                clone = clone.check_capture(color, (x, y));
+                            }
                             if !clone.five_interrupted(color) {
                                 Move::FiveNotInterrupted
                             }
@@ -250,7 +262,7 @@ impl Board {
             BoardState::Victory(Square::Black)
         }
         else if *color == Square::Black && self.five_aligned(pos, color) {
-            if !self.check_interruptable(pos, color) {
+            if !self.rules.captures || !self.check_interruptable(pos, color) {
                 BoardState::Victory(Square::Black)
             }
             else {
@@ -261,7 +273,7 @@ impl Board {
             BoardState::Victory(Square::White)
         }
         else if *color == Square::White && self.five_aligned(pos, color) {
-            if !self.check_interruptable(pos, color) {
+            if !self.rules.captures || !self.check_interruptable(pos, color) {
                 BoardState::Victory(Square::White)
             } else {
                 BoardState::FiveAligned(Square::White, pos)
@@ -346,6 +358,6 @@ impl Board {
     }
 
     pub fn evaluation(&self, player: &Square, current_player: &Square) -> i32 {
-        self.check_patterns(player, current_player)
+        self.check_patterns(player, current_player, &Weights::new())
     }
 }
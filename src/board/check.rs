@@ -1,171 +1,90 @@
-use board::board::{Board, Right, Move};
+use board::board::{Board, Move};
+use board::bitboard::{Line, LineBits, Pattern, LINES};
+use board::patterns::Weights;
 use board::square::Square;
 
+use board::itertools::Itertools;
 use board::time::{PreciseTime};
 
+lazy_static! {
+    static ref THREAT_PATTERNS: Vec<(Pattern, Vec<usize>)> = vec![
+        ("WWWW-", vec![4]), ("BBBB-", vec![4]),
+        ("WWWW-W", vec![4]), ("BBBB-B", vec![4]),
+        ("WW-WW", vec![2]), ("BB-BB", vec![2]),
+        ("W-WWW", vec![1]), ("B-BBB", vec![1]),
+        ("-WWWW", vec![0]), ("-BBBB", vec![0]),
+        ("--WWW", vec![1]), ("--BBB", vec![1]),
+        ("WWW--", vec![3]), ("BBB--", vec![3]),
+        ("-WWW-", vec![0, 4]), ("-BBB-", vec![0, 4]),
+        ("W-WW-", vec![1, 4]), ("B-BB-", vec![1, 4]),
+        ("-WW-W", vec![3, 0]), ("-BB-B", vec![3, 0])]
+        .into_iter().map(|(s, v)| (Pattern::from(s), v)).collect();
+
+    static ref ORDERING_PATTERNS: [Vec<(Pattern, i32)>; 2] = [
+        ordering_patterns(&Square::Black),
+        ordering_patterns(&Square::White),
+    ];
+}
+
+// Fives, fours, open threes and captures, by decreasing priority.
+fn ordering_patterns(color: &Square) -> Vec<(Pattern, i32)> {
+    vec![("xxxxx", 10000),
+         ("xxxx-", 1000), ("-xxxx", 1000), ("xxx-x", 1000), ("x-xxx", 1000), ("xx-xx", 1000),
+         ("-xxx-", 100), ("-xx-x-", 100), ("-x-xx-", 100),
+         ("xyyx", 50)]
+        .into_iter().map(|(s, score)| (Pattern::from_template(s, color), score)).collect()
+}
+
 impl Board
 {
-    pub fn check_moveintocapture(&self, color: &Square, pos: (usize, usize)) -> bool {
-        let (x, y) = pos;
-
-        let capture = |s|
-        {
-            s == "xyyx".replace("x", color.opposite().to_str()).replace("y", color.to_str())
-        };
+    pub fn line_through(&self, pos: (usize, usize), dir: usize) -> (&'static Line, LineBits, usize) {
+        let (index, offset) = Line::through(pos, dir);
+        (&LINES[index], self.bits.line(&LINES[index]), offset)
+    }
 
-        // East
-        if x + 2 < 19 && x >= 1 {
-            if capture((0..4).map(|i|
-                                  if x + i - 1 == x { color.to_char() }
-                                  else { self.state[x + i - 1][y].to_char() })
-                       .collect::<String>()) {
-                return true;
-            };
-        }
-        // West
-        if x + 1 < 19 && x >= 2 {
-            if capture((0..4).map(|i| 
-                                  if x - i + 1 == x { color.to_char() }
-                                  else { self.state[x - i + 1][y].to_char() })
-                       .collect::<String>()) {
-                return true;
-            };
-        }
-        // South
-        if y + 2 < 19 && y >= 1 {
-            if capture((0..4).map(|i|
-                                  if y + i - 1 == y { color.to_char() }
-                                  else { self.state[x][y + i - 1].to_char() })
-                       .collect::<String>()) {
-                return true;
-            };
-        }
-        // North
-        if y + 1 < 19 && y >= 2 {
-            if capture((0..4).map(|i|
-                                  if y - i + 1 == y { color.to_char() } 
-                                  else { self.state[x][y - i + 1].to_char() })
-                       .collect::<String>()) {
-                return true;
-            };
-        }
-        // North-East
-        if y + 1 < 19 && y >= 2 && x + 2 < 19 && x >= 1 {
-            if capture((0..4).map(|i| 
-                                  if x + i - 1 == x && y - i + 1 == y { color.to_char() }
-                                  else { self.state[x + i - 1][y - i + 1].to_char() })
-                       .collect::<String>()) {
-                return true;
-            };
-        }
-        // North-West
-        if y + 1 < 19 && y >= 2 && x + 1 < 19 && x >= 2 {
-            if capture((0..4).map(|i|
-                                  if x - i + 1 == x && y - i + 1 == y { color.to_char() } 
-                                  else { self.state[x - i + 1][y - i + 1].to_char() })
-                       .collect::<String>()) {
-                return true;
-            };
-        }
-        // South-East
-        if y + 2 < 19 && y >= 1 && x + 2 < 19 && x >= 1 {
-            if capture((0..4).map(|i|
-                                  if x + i - 1 == x && y + i - 1 == y { color.to_char() }
-                                  else { self.state[x + i - 1][y + i - 1].to_char()})
-                       .collect::<String>()) {
-                return true;
-            };
-        }
-        // South-West
-        if y + 2 < 19 && y >= 1 && x + 1 < 19 && x >= 2 {
-            if capture((0..4).map(|i|
-                                  if x - i + 1 == x && y + i - 1 == y { color.to_char() }
-                                  else { self.state[x - i + 1][y + i - 1].to_char()})
-                       .collect::<String>()) {
-                return true;
-            };
-        }
-        false
+    pub fn check_moveintocapture(&self, color: &Square, pos: (usize, usize)) -> bool {
+        let pattern = Pattern::from_template("yxxy", color);
+        (0..4).any(|dir| {
+            let (_, line, i) = self.line_through(pos, dir);
+            let line = line.with(i, color);
+            line.matches_at(&pattern, i as i32 - 1) || line.matches_at(&pattern, i as i32 - 2)
+        })
     }
 
     pub fn check_capture(&self, color: &Square, pos: (usize, usize)) -> Board {
-        let (x, y) = pos;
         let mut board = self.clone();
-        {
-            let mut capture = |right: Right|
-                if right.data == "xyyx".replace("x", color.to_str()).
-                    replace("y", color.opposite().to_str()) {
-                        let (xb, yb) = (right.fun)(1);
-                        let (xc, yc) = (right.fun)(2);
-                        board.state[xb][yb] = Square::Empty;
-                        board.state[xc][yc] = Square::Empty;
-                        board.add_move((xb, yb), &color.opposite());
-                        board.add_move((xc, yc), &color.opposite());
-                        board.b_capture +=
-                            if *color == Square::Black { 2 } else { 0 };
-                        board.w_capture +=
-                            if *color == Square::White { 2 } else { 0 };
-                    };
-
-            // East
-            if x + 3 < 19 {
-                capture(Right { data: (0..4).map(|i| self.state[x + i][y].to_char())
-                    .collect::<String>(),
-                    fun: Box::new(move |i| (x + i, y))})
-            }
-            // West
-            if x >= 3 { 
-                capture(Right { data: (0..4).map(|i| self.state[x - i][y].to_char())
-                    .collect::<String>(),
-                    fun: Box::new(move |i| (x - i, y))})
-            }
-            // South
-            if y + 3 < 19 { 
-                capture(Right { data: (0..4).map(|i| self.state[x][y + i].to_char())
-                    .collect::<String>(),
-                    fun: Box::new(move |i| (x, y + i))})
-            }
-            // North
-            if y >= 3 { 
-                capture(Right { data: (0..4).map(|i| self.state[x][y - i].to_char())
-                    .collect::<String>(),
-                    fun: Box::new(move |i| (x, y - i))})
-            }
-            // South-East
-            if x + 3 < 19 && y + 3 < 19 {
-                capture(Right { data: (0..4).map(|i| self.state[x + i][y + i].to_char())
-                    .collect::<String>(),
-                    fun: Box::new(move |i| (x + i, y + i))})
-            }
-            // North-East
-            if x + 3 < 19 && y >= 3 {
-                capture(Right { data: (0..4).map(|i| self.state[x + i][y - i].to_char())
-                    .collect::<String>(),
-                    fun: Box::new(move |i| (x + i, y - i))})
-            }
-            // South-West
-            if x >= 3 && y + 3 < 19 {
-                capture(Right { data: (0..4).map(|i| self.state[x - i][y + i].to_char())
-                    .collect::<String>(),
-                    fun: Box::new(move |i| (x - i, y + i))})
-            }
-            // North-West
-            if x >= 3 && y >= 3 {
-                capture(Right { data: (0..4).map(|i| self.state[x - i][y - i].to_char())
-                    .collect::<String>(),
-                    fun: Box::new(move |i| (x - i, y - i))})
+        let pattern = Pattern::from_template("xyyx", color);
+        for dir in 0..4 {
+            let (line_def, line, i) = self.line_through(pos, dir);
+            let i = i as i32;
+            for &(start, step) in &[(i, 1), (i - 3, -1)] {
+                if line.matches_at(&pattern, start) {
+                    for captured in &[i + step, i + 2 * step] {
+                        let pos_cap = line_def.pos(*captured as usize);
+                        board.set(pos_cap, &Square::Empty);
+                        board.add_move(pos_cap, &color.opposite());
+                    }
+                    board.b_capture +=
+                        if *color == Square::Black { 2 } else { 0 };
+                    board.w_capture +=
+                        if *color == Square::White { 2 } else { 0 };
+                }
             }
         }
-        board.clone()
+        board
     }
 
-    fn get_positions(p: Vec<(&'static str, Vec<usize>)>, t: Vec<Right>) -> Vec<(usize, usize)> {
+    fn get_positions(&self, p: &[(Pattern, Vec<usize>)]) -> Vec<(usize, usize)> {
         let mut pos = Vec::new();
-        for right in t {
-            for &(ref pattern, ref vec) in &p {
-                if let Some(offset) = right.data.find(pattern) {
+        for line_def in LINES.iter() {
+            let line = self.bits.line(line_def);
+            if line.is_empty() {
+                continue;
+            }
+            for &(ref pattern, ref vec) in p {
+                if let Some(offset) = line.find(pattern) {
                     for i in vec {
-                        pos.push((right.fun)(i + offset));
+                        pos.push(line_def.pos(i + offset));
                     }
                 }
             }
@@ -173,20 +92,76 @@ impl Board
         pos
     }
 
-    pub fn check_threats(&self, color: &Square) -> Vec<(usize, usize)> {
-        let p = vec![("WWWW-", vec![4]), ("BBBB-", vec![4]),
-        ("WWWW-W", vec![4]), ("BBBB-B", vec![4]),
-        ("WW-WW", vec![2]), ("BB-BB", vec![2]),
-        ("W-WWW", vec![1]), ("B-BBB", vec![1]),
-        ("-WWWW", vec![0]), ("-BBBB", vec![0]),
-        ("--WWW", vec![1]), ("--BBB", vec![1]),
-        ("WWW--", vec![3]), ("BBB--", vec![3]),
-        ("-WWW-", vec![0, 4]), ("-BBB-", vec![0, 4]),
-        ("W-WW-", vec![1, 4]), ("B-BB-", vec![1, 4]),
-        ("-WW-W", vec![3, 0]), ("-BB-B", vec![3, 0])];
+    // Offsets of the `size`-wide windows of a line holding `stones` stones of
+    // `color` and no opponent stone. When `open` is set, both ends of the
+    // window must be empty and only its inner squares are counted.
+    fn find_windows(line: &LineBits, color: &Square, size: usize, stones: u32, open: bool) -> Vec<usize> {
+        if line.len < size || line.own(color).count_ones() < stones {
+            return vec![];
+        }
+        let full = (1 << size) - 1;
+        let ends = if open { 1 | 1 << (size - 1) } else { 0 };
+        (0..line.len - size + 1).filter(|&start| {
+            let own = line.own(color) >> start;
+            let empty = line.empty() >> start;
+            (line.own(&color.opposite()) >> start) & full == 0
+                && (empty & ends) == ends
+                && (own & full & !ends).count_ones() == stones
+        }).collect()
+    }
+
+    // Empty squares among the `mask` squares of the window starting at `start`.
+    fn free_squares(line_def: &Line, line: &LineBits, start: usize, mask: u32) -> Vec<(usize, usize)> {
+        let mut free = (line.empty() >> start) & mask;
+        let mut squares = Vec::new();
+        while free != 0 {
+            squares.push(line_def.pos(start + free.trailing_zeros() as usize));
+            free &= free - 1;
+        }
+        squares
+    }
+
+    fn board_window_squares(&self, color: &Square, size: usize, stones: u32, open: bool) -> Vec<(usize, usize)> {
+        let inner = ((1 << size) - 1) & if open { !(1 | 1 << (size - 1)) } else { !0 };
+        LINES.iter().fold(vec![], |mut acc, line_def| {
+            let line = self.bits.line(line_def);
+            for start in Board::find_windows(&line, color, size, stones, open) {
+                acc.extend(Board::free_squares(line_def, &line, start, inner));
+            }
+            acc
+        }).into_iter().unique().collect()
+    }
+
+    // Squares where `color` would align five stones.
+    pub fn check_five_squares(&self, color: &Square) -> Vec<(usize, usize)> {
+        self.board_window_squares(color, 5, 4, false)
+    }
+
+    // Squares where `color` would make a four.
+    pub fn check_four_squares(&self, color: &Square) -> Vec<(usize, usize)> {
+        self.board_window_squares(color, 5, 3, false)
+    }
 
-        let t = self.explode();
-        Board::get_positions(p, t)
+    // Squares where `color` would make an open three.
+    pub fn check_three_squares(&self, color: &Square) -> Vec<(usize, usize)> {
+        self.board_window_squares(color, 6, 2, true)
+    }
+
+    // Squares blocking the open threes of `color` going through `pos`.
+    pub fn check_three_defenses(&self, pos: (usize, usize), color: &Square) -> Vec<(usize, usize)> {
+        (0..4).fold(vec![], |mut acc, dir| {
+            let (line_def, line, i) = self.line_through(pos, dir);
+            for start in Board::find_windows(&line, color, 6, 3, true) {
+                if start <= i && i < start + 6 {
+                    acc.extend(Board::free_squares(line_def, &line, start, (1 << 6) - 1));
+                }
+            }
+            acc
+        }).into_iter().unique().collect()
+    }
+
+    pub fn check_threats(&self, color: &Square) -> Vec<(usize, usize)> {
+        self.get_positions(&THREAT_PATTERNS)
             .into_iter()
             .filter(|&x| !self.check_moveintocapture(color, x))
             .collect::<Vec<_>>()
@@ -194,19 +169,34 @@ impl Board
 
     pub fn check_capture_pos(&self, color: &Square) -> Vec<(usize, usize)>
     {
-        let p = match *color { 
-            Square::Black => vec![("BWW-", vec![3]), ("-WWB", vec![0])],
-            Square::White => vec![("-BBW", vec![0]), ("WBB-", vec![3])],
+        let p = match *color {
+            Square::Black => vec![(Pattern::from("BWW-"), vec![3]), (Pattern::from("-WWB"), vec![0])],
+            Square::White => vec![(Pattern::from("-BBW"), vec![0]), (Pattern::from("WBB-"), vec![3])],
             Square::Empty => vec![],
         };
 
-        let t = self.explode();
-        Board::get_positions(p, t)
+        self.get_positions(&p)
             .into_iter()
             .filter(|&x| !self.check_moveintocapture(color, x))
             .collect::<Vec<_>>()
     }
 
+    // Every square where `color` can capture, unlike `check_capture_pos`
+    // which only reports the first capture found on each line.
+    pub fn check_capture_squares(&self, color: &Square) -> Vec<(usize, usize)> {
+        let patterns = [(Pattern::from_template("xyy-", color), 3), (Pattern::from_template("-yyx", color), 0)];
+        LINES.iter().fold(vec![], |mut acc, line_def| {
+            let line = self.bits.line(line_def);
+            for &(ref pattern, i) in &patterns {
+                acc.extend(line.find_all(pattern).into_iter().map(|offset| line_def.pos(offset + i)));
+            }
+            acc
+        }).into_iter()
+            .unique()
+            .filter(|&x| !self.check_moveintocapture(color, x))
+            .collect()
+    }
+
     pub fn check_interruptable(&self, pos: (usize, usize), color: &Square) -> bool {
         let possible_captures = self.check_capture_pos(&color.opposite());
         possible_captures.iter().fold(false, |acc, &pos_cap| {
@@ -219,111 +209,85 @@ impl Board
         })
     }
 
-    fn rec_explo(&self, color: &Square, x: i32, y: i32,
-                 add_x: i32, add_y: i32, acc: i32) -> i32 {
-        if acc > 4 || x + add_x > 18 || y + add_y > 18
-            || x + add_x < 0 || y + add_y < 0
-                || self.state[(x + add_x) as usize][(y + add_y) as usize]
-                != *color {
-                    acc
-                }
-        else {
-            self.rec_explo(color, x + add_x, y + add_y, add_x, add_y, acc + 1)
-        }
-    }
-
     pub fn five_aligned(&self, pos: (usize, usize), color: &Square) -> bool {
-        let (x, y) = (pos.0 as i32, pos.1 as i32);
-        if self.state[x as usize][y as usize] != *color {
+        if self.state[pos.0][pos.1] != *color {
             return false;
         }
-        (self.rec_explo(color, x, y, 1, 1, 1)
-         + self.rec_explo(color, x, y, -1, -1, 0)) > 4
-            || (self.rec_explo(color, x, y, 1, 0, 1)
-                + self.rec_explo(color, x, y, -1, 0, 0)) > 4
-            || (self.rec_explo(color, x, y, 0, 1, 1)
-                + self.rec_explo(color, x, y, 0, -1, 0)) > 4
-            || (self.rec_explo(color, x, y, 1, -1, 1)
-                + self.rec_explo(color, x, y, -1, 1, 0)) > 4
+        (0..4).any(|dir| {
+            let (_, line, i) = self.line_through(pos, dir);
+            line.run_through(color, i) > 4
+        })
+    }
+
+    // Stones of the alignments of five or more of `color` through `pos`, one
+    // run per direction.
+    pub fn aligned_squares(&self, pos: (usize, usize), color: &Square) -> Vec<Vec<(usize, usize)>> {
+        (0..4).filter_map(|dir| {
+            let (line_def, line, i) = self.line_through(pos, dir);
+            if line.run_through(color, i) > 4 {
+                let own = line.own(color);
+                let start = (0..i + 1).rev().take_while(|&k| own & (1 << k) != 0).last().unwrap();
+                Some((start..line.len).take_while(|&k| own & (1 << k) != 0).map(|k| line_def.pos(k)).collect())
+            } else {
+                None
+            }
+        }).collect()
     }
 
-    pub fn check_patterns(&self, color: &Square, current_color: &Square) -> i32 {
-        let patterns = vec![("xxxxx", 100240), ("xxxx-", 1280), ("-xxxx", 1280),
-        ("xxx-x", 1280), ("x-xxx", 1280), ("xx-xx", 1280), ("xxx--", 160),
-        ("--xxx", 160), ("-xxx-", 160), ("-x-xx", 40), ("xx-x-", 40),
-        ("--xx-", 10), ("-xx--", 10), ("yxx-", -80), ("-xxy", -80)];
-        let player_patterns = patterns.iter().map(|&(s, score)|
-                                                  (s.replace("x", color.to_str())
-                                                   .replace("y", color.opposite().to_str()),
-                                                   if *color != *current_color {
-                                                       (score as f64 * 0.75) as i32
-                                                   } else {
-                                                       score
-                                                   })).collect::<Vec<_>>();
-        let opponent_patterns = patterns.iter().map(|&(s, score)|
-                                                    (s.replace("x", color.opposite().to_str())
-                                                     .replace("y", color.to_str()),
-                                                     if color.opposite() != *current_color {
-                                                         (-score as f64 * 0.75) as i32
-                                                     } else {
-                                                         -score
-                                                     })).collect::<Vec<_>>();
+    // Cheap score of the move `pos` of `color`, used to order the search: the
+    // patterns it makes, plus half of those it prevents the opponent from making.
+    pub fn move_score(&self, pos: (usize, usize), color: &Square) -> i32 {
+        let opponent = color.opposite();
+        (0..4).fold(0, |acc, dir| {
+            let (_, line, i) = self.line_through(pos, dir);
+            acc + Board::ordering_score(&line.with(i, color), i, color)
+                + Board::ordering_score(&line.with(i, &opponent), i, &opponent) / 2
+        })
+    }
+
+    fn ordering_score(line: &LineBits, i: usize, color: &Square) -> i32 {
+        let patterns = match *color {
+            Square::White => &ORDERING_PATTERNS[1],
+            _ => &ORDERING_PATTERNS[0],
+        };
+        patterns.iter()
+            .filter(|&&(ref pattern, _)| (0..pattern.len).any(|k| line.matches_at(pattern, i as i32 - k as i32)))
+            .fold(0, |acc, &(_, score)| acc + score)
+    }
 
-        let t = self.explode();
-        let capture_heuristic = |x| if x >= 10 { 500000 } else { x * x * x * x + 160};
-        t.iter().fold(0, |acc, right| 
-                      acc + player_patterns.iter().chain(opponent_patterns.iter())
-                      .fold(0, |acc, &(ref pattern, score)|
-                            if right.data.find(pattern).is_some() {
-                                acc + score
-                            } else {
-                                acc
-                            }))
-        + if *color != *current_color { 
-            (capture_heuristic(self.get_score(color)) as f64 * 0.75) as i32
+    pub fn check_patterns(&self, color: &Square, current_color: &Square, weights: &Weights) -> i32 {
+        let opponent = color.opposite();
+        let weight = |c: &Square, score: i32| if *c != *current_color {
+            (score as f64 * weights.waiting_factor) as i32
         } else {
-            capture_heuristic(self.get_score(color))
-        }
-        - if color.opposite() != *current_color {
-            (capture_heuristic(self.get_score(&color.opposite())) as f64 * 0.75) as i32
+            score
+        };
+        let patterns = weights.patterns.iter().enumerate().fold(0, |acc, (i, &score)|
+            acc + self.patterns.count(color, i) * weight(color, score)
+            + self.patterns.count(&opponent, i) * weight(&opponent, -score));
+
+        let capture_heuristic = |x: i32| if x >= 10 {
+            weights.capture_win
         } else {
-            capture_heuristic(self.get_score(&color.opposite()))
-        }
+            x.pow(weights.capture_exponent) + weights.capture_base
+        };
+        patterns
+            + weight(color, capture_heuristic(self.get_score(color)))
+            - weight(&opponent, capture_heuristic(self.get_score(&opponent)))
     }
 
     pub fn check_free_threes(&self, x: i32, y: i32, color: &Square) -> bool {
-        let sq_to_char = |sq: &Square| match *sq {
-            Square::Black => 'B', Square::White => 'W', Square::Empty => ' '
-        };
         let p = vec![" x xx ", " xx x ", "  xxx ", " xxx  "].iter()
-            .map(|s| s.replace("x", match *color {
-                Square::Black => "B", Square::White => "W", _ => " "
-            }))
-        .collect::<Vec<String>>();
-        let mut t = vec![String::new(); 4];
-        t[0] = (0..9).map(|i| i as i32 - 4)
-            .filter(|i| (x + i) < 19 && (x + i) >= 0)
-            .map(|i| sq_to_char(&self.state[(x + i) as usize][y as usize]))
-            .collect::<String>();
-        t[1] = (0..9).map(|i| i as i32 - 4)
-            .filter(|i| y + i < 19 && y + i >= 0)
-            .map(|i| sq_to_char(&self.state[x as usize][(y + i) as usize]))
-            .collect::<String>();
-        t[2] = (0..9).map(|i| i as i32 - 4)
-            .filter(|i| x + i < 19 && x + i >= 0 && y + i < 19 && y + i >= 0)
-            .map(|i| sq_to_char(&self.state[(x + i) as usize][(y + i) as usize]))
-            .collect::<String>();
-        t[3] = (0..9).map(|i| i as i32 - 4)
-            .filter(|i| x + i < 19 && x + i >= 0 && y - i < 19 && y - i >= 0)
-            .map(|i| sq_to_char(&self.state[(x + i) as usize][(y - i) as usize]))
-            .collect::<String>();
-        t.iter().filter(|s| s.find(&p[0]).is_some()
-                        || s.find(&p[1]).is_some()
-                        || s.find(&p[2]).is_some()
-                        || s.find(&p[3]).is_some()).count() > 1
+            .map(|s| Pattern::from_template(s, color))
+            .collect::<Vec<Pattern>>();
+        (0..4).filter(|&dir| {
+            let (_, line, i) = self.line_through((x as usize, y as usize), dir);
+            let window = line.slice(i as i32 - 4, i as i32 + 5);
+            p.iter().any(|pattern| window.find(pattern).is_some())
+        }).count() > 1
     }
 
     pub fn check_full_board(&self) -> bool {
-        !self.state.iter().any(|e| e.iter().any(|i| *i == Square::Empty))
+        self.bits.is_full()
     }
 }
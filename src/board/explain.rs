@@ -0,0 +1,114 @@
+use board::board::Board;
+use board::bitboard::{Pattern, LINES};
+use board::patterns::{Weights, EVAL_PATTERNS};
+use board::square::Square;
+
+use std::fmt;
+
+// A pattern of `EVAL_PATTERNS` found on a line, as counted by the evaluation.
+#[derive(Clone, Debug)]
+pub struct PatternMatch
+{
+    pub color: Square,
+    pub pattern: &'static str,
+    // Index of the line in `LINES`
+    pub line: usize,
+    pub squares: Vec<(usize, usize)>,
+    // Contribution to the score of the evaluated player
+    pub score: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct CaptureTerm
+{
+    pub color: Square,
+    pub captured: i32,
+    pub score: i32,
+}
+
+// Detail of `Board::check_patterns`: the total is the sum of the scores of
+// every pattern and of both capture terms.
+#[derive(Clone, Debug)]
+pub struct Explanation
+{
+    pub player: Square,
+    pub current_player: Square,
+    pub patterns: Vec<PatternMatch>,
+    pub captures: Vec<CaptureTerm>,
+    pub total: i32,
+}
+
+impl Explanation
+{
+    // Sum of the contributions of the patterns of `color`.
+    pub fn pattern_score(&self, color: &Square) -> i32 {
+        self.patterns.iter().filter(|m| m.color == *color).fold(0, |acc, m| acc + m.score)
+    }
+}
+
+impl fmt::Display for Explanation
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Evaluation for {}, {} to move: {}", self.player, self.current_player, self.total)?;
+        for color in &[self.player.clone(), self.player.opposite()] {
+            writeln!(f, "{} patterns: {}", color, self.pattern_score(color))?;
+            for m in self.patterns.iter().filter(|m| m.color == *color) {
+                let squares = m.squares.iter().map(|&pos| Board::notation(pos)).collect::<Vec<_>>();
+                writeln!(f, "  {:<6} {:>8}  line {:>3}  {}", m.pattern, m.score, m.line, squares.join(" "))?;
+            }
+        }
+        for capture in &self.captures {
+            writeln!(f, "{} captures: {} stones, {}", capture.color, capture.captured, capture.score)?;
+        }
+        Ok(())
+    }
+}
+
+impl Board
+{
+    pub fn explain_evaluation(&self, player: &Square, current_player: &Square, weights: &Weights) -> Explanation {
+        let weight = |c: &Square, score: i32| if *c != *current_player {
+            (score as f64 * weights.waiting_factor) as i32
+        } else {
+            score
+        };
+        let mut patterns = Vec::new();
+        for color in &[player.clone(), player.opposite()] {
+            let sign = if *color == *player { 1 } else { -1 };
+            for (index, line) in LINES.iter().enumerate() {
+                let matched = self.patterns.line_matches(index, color);
+                for (i, &(template, _)) in EVAL_PATTERNS.iter().enumerate().filter(|&(i, _)| matched & 1 << i != 0) {
+                    let pattern = Pattern::from_template(template, color);
+                    let offset = self.bits.line(line).find(&pattern).unwrap();
+                    patterns.push(PatternMatch {
+                        color: color.clone(),
+                        pattern: template,
+                        line: index,
+                        squares: (offset..offset + pattern.len).map(|k| line.pos(k)).collect(),
+                        score: weight(color, sign * weights.patterns[i]),
+                    });
+                }
+            }
+        }
+
+        let capture_heuristic = |x: i32| if x >= 10 {
+            weights.capture_win
+        } else {
+            x.pow(weights.capture_exponent) + weights.capture_base
+        };
+        let captures = vec![
+            CaptureTerm { color: player.clone(), captured: self.get_score(player),
+                          score: weight(player, capture_heuristic(self.get_score(player))) },
+            CaptureTerm { color: player.opposite(), captured: self.get_score(&player.opposite()),
+                          score: -weight(&player.opposite(), capture_heuristic(self.get_score(&player.opposite()))) },
+        ];
+        let total = patterns.iter().fold(0, |acc, m| acc + m.score) + captures.iter().fold(0, |acc, c| acc + c.score);
+        Explanation {
+            player: player.clone(),
+            current_player: current_player.clone(),
+            patterns: patterns,
+            captures: captures,
+            total: total,
+        }
+    }
+}
@@ -2,11 +2,21 @@ extern crate itertools;
 extern crate rand;
 extern crate time;
 
+mod bitboard;
 mod check;
+mod explain;
+mod patterns;
+mod rules;
 mod square;
+mod symmetry;
 mod board;
 
+pub use self::bitboard::{BitBoard, Line, LineBits, Pattern, LINES};
+pub use self::explain::{CaptureTerm, Explanation, PatternMatch};
+pub use self::patterns::{PatternCache, Weights, EVAL_PATTERNS, MAX_EVALUATION};
+pub use self::rules::{Rules, VARIANTS};
 pub use self::board::Board;
 pub use self::board::BoardState;
 pub use self::board::Move;
 pub use self::square::Square;
+pub use self::symmetry::{Symmetry, SYMMETRIES};
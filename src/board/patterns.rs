@@ -0,0 +1,130 @@
+use board::square::Square;
+use board::bitboard::{BitBoard, Line, Pattern, LINES};
+
+use std::cmp;
+
+// Evaluation patterns, 'x' standing for the evaluated color and 'y' for its
+// opponent, with their base score.
+pub const EVAL_PATTERNS: [(&'static str, i32); 15] = [
+    ("xxxxx", 100240), ("xxxx-", 1280), ("-xxxx", 1280),
+    ("xxx-x", 1280), ("x-xxx", 1280), ("xx-xx", 1280), ("xxx--", 160),
+    ("--xxx", 160), ("-xxx-", 160), ("-x-xx", 40), ("xx-x-", 40),
+    ("--xx-", 10), ("-xx--", 10), ("yxx-", -80), ("-xxy", -80)];
+
+// Bound of the static evaluation, far below the win and loss scores of the
+// search.
+pub const MAX_EVALUATION: i64 = 100_000_000;
+
+// Scores given by the static evaluation, see `Board::check_patterns`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Weights
+{
+    // Score of each pattern of `EVAL_PATTERNS`
+    pub patterns: [i32; 15],
+    // Factor applied to the scores of the player who is not to move
+    pub waiting_factor: f64,
+    // Score of a victory by capture
+    pub capture_win: i32,
+    // Captured stones `n` are worth `n ^ capture_exponent + capture_base`
+    pub capture_base: i32,
+    pub capture_exponent: u32,
+}
+
+impl Weights
+{
+    pub fn new() -> Weights {
+        let mut patterns = [0; 15];
+        for (i, &(_, score)) in EVAL_PATTERNS.iter().enumerate() {
+            patterns[i] = score;
+        }
+        Weights {
+            patterns: patterns,
+            waiting_factor: 0.75,
+            capture_win: 500000,
+            capture_base: 160,
+            capture_exponent: 4,
+        }
+    }
+
+    // Largest magnitude the evaluation can reach, with every pattern found on
+    // every line for both colors and a `waiting_factor` of at most 1.
+    pub fn max_evaluation(&self) -> i64 {
+        let lines = 2 * LINES.len() as i64;
+        let patterns = self.patterns.iter().fold(0, |acc, &score| acc + lines * (score as i64).abs());
+        let captures = cmp::max((self.capture_win as i64).abs(),
+                                9i64.pow(self.capture_exponent) + (self.capture_base as i64).abs());
+        patterns + 2 * captures
+    }
+}
+
+lazy_static! {
+    static ref COMPILED_PATTERNS: [Vec<Pattern>; 2] = [
+        EVAL_PATTERNS.iter().map(|&(s, _)| Pattern::from_template(s, &Square::Black)).collect(),
+        EVAL_PATTERNS.iter().map(|&(s, _)| Pattern::from_template(s, &Square::White)).collect(),
+    ];
+}
+
+// For every line, the set of evaluation patterns found on it for each color,
+// along with the number of lines matching each pattern. Only the four lines
+// going through a modified square are rescanned.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PatternCache
+{
+    lines: Vec<[u16; 2]>,
+    counts: [[i32; 15]; 2],
+}
+
+fn color_index(color: &Square) -> usize {
+    match *color {
+        Square::White => 1,
+        _ => 0,
+    }
+}
+
+impl PatternCache
+{
+    pub fn new() -> PatternCache {
+        PatternCache { lines: vec![[0, 0]; LINES.len()], counts: [[0; 15]; 2] }
+    }
+
+    pub fn from_bits(bits: &BitBoard) -> PatternCache {
+        let mut cache = PatternCache::new();
+        for index in 0..LINES.len() {
+            cache.update_line(bits, index);
+        }
+        cache
+    }
+
+    pub fn update(&mut self, bits: &BitBoard, pos: (usize, usize)) {
+        for dir in 0..4 {
+            let (index, _) = Line::through(pos, dir);
+            self.update_line(bits, index);
+        }
+    }
+
+    fn update_line(&mut self, bits: &BitBoard, index: usize) {
+        let line = bits.line(&LINES[index]);
+        for c in 0..2 {
+            let matched = if line.is_empty() { 0 } else {
+                COMPILED_PATTERNS[c].iter().enumerate()
+                    .filter(|&(_, pattern)| line.find(pattern).is_some())
+                    .fold(0u16, |acc, (i, _)| acc | 1 << i)
+            };
+            let old = self.lines[index][c];
+            for i in 0..EVAL_PATTERNS.len() {
+                self.counts[c][i] += ((matched >> i) & 1) as i32 - ((old >> i) & 1) as i32;
+            }
+            self.lines[index][c] = matched;
+        }
+    }
+
+    // Number of lines on which pattern `i` of `EVAL_PATTERNS` is found for `color`.
+    pub fn count(&self, color: &Square, i: usize) -> i32 {
+        self.counts[color_index(color)][i]
+    }
+
+    // Bitset of the patterns of `EVAL_PATTERNS` found on line `index` for `color`.
+    pub fn line_matches(&self, index: usize, color: &Square) -> u16 {
+        self.lines[index][color_index(color)]
+    }
+}
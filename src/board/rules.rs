@@ -0,0 +1,32 @@
+// Rules of the game that can be turned off, the standard game using both.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rules
+{
+    // Pairs of stones can be captured, winning at ten captured stones and
+    // breaking a five
+    pub captures: bool,
+    // Moves making two free threes at once are forbidden
+    pub double_threes: bool,
+}
+
+pub const VARIANTS: [(&'static str, Rules); 4] = [
+    ("standard", Rules { captures: true, double_threes: true }),
+    ("no_captures", Rules { captures: false, double_threes: true }),
+    ("no_double_threes", Rules { captures: true, double_threes: false }),
+    ("freestyle", Rules { captures: false, double_threes: false }),
+];
+
+impl Rules
+{
+    pub fn standard() -> Rules {
+        VARIANTS[0].1
+    }
+
+    pub fn name(&self) -> &'static str {
+        VARIANTS.iter().find(|&&(_, rules)| rules == *self).unwrap().0
+    }
+
+    pub fn from_name(name: &str) -> Option<Rules> {
+        VARIANTS.iter().find(|&&(n, _)| n == name).map(|&(_, rules)| rules)
+    }
+}
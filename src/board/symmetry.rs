@@ -0,0 +1,97 @@
+use board::board::{Board, BoardState};
+use board::square::Square;
+
+// The 8 symmetries of the board: the 4 rotations, then the 4 reflections.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Symmetry
+{
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipX,
+    FlipY,
+    Transpose,
+    AntiTranspose,
+}
+
+pub const SYMMETRIES: [Symmetry; 8] = [
+    Symmetry::Identity, Symmetry::Rotate90, Symmetry::Rotate180, Symmetry::Rotate270,
+    Symmetry::FlipX, Symmetry::FlipY, Symmetry::Transpose, Symmetry::AntiTranspose,
+];
+
+impl Symmetry
+{
+    // Image of `pos`, moves of a board being mapped to the same moves on
+    // the transformed board.
+    pub fn apply(&self, pos: (usize, usize)) -> (usize, usize) {
+        let (x, y) = pos;
+        match *self {
+            Symmetry::Identity => (x, y),
+            Symmetry::Rotate90 => (18 - y, x),
+            Symmetry::Rotate180 => (18 - x, 18 - y),
+            Symmetry::Rotate270 => (y, 18 - x),
+            Symmetry::FlipX => (18 - x, y),
+            Symmetry::FlipY => (x, 18 - y),
+            Symmetry::Transpose => (y, x),
+            Symmetry::AntiTranspose => (18 - y, 18 - x),
+        }
+    }
+
+    pub fn inverse(&self) -> Symmetry {
+        match *self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            s => s,
+        }
+    }
+
+    // Symmetry applying `self`, then `other`.
+    pub fn then(&self, other: Symmetry) -> Symmetry {
+        // The images of (0, 1) are all different
+        let image = other.apply(self.apply((0, 1)));
+        *SYMMETRIES.iter().find(|s| s.apply((0, 1)) == image).unwrap()
+    }
+}
+
+impl Board
+{
+    pub fn transform(&self, symmetry: Symmetry) -> Board {
+        let mut board = Board::with_rules(self.rules);
+        for x in 0..19 {
+            for y in 0..19 {
+                if self.state[x][y] != Square::Empty {
+                    board.set(symmetry.apply((x, y)), &self.state[x][y]);
+                }
+            }
+        }
+        board.b_capture = self.b_capture;
+        board.w_capture = self.w_capture;
+        board.game_state = match self.game_state {
+            BoardState::FiveAligned(ref color, pos) => BoardState::FiveAligned(color.clone(), symmetry.apply(pos)),
+            ref state => state.clone(),
+        };
+        board.generate_hash();
+        board
+    }
+
+    // Hash of the board transformed by `symmetry`, without building it.
+    pub fn symmetric_hash(&self, symmetry: Symmetry) -> u64 {
+        (0..19).flat_map(|x| (0..19).map(move |y| (x, y)))
+            .filter(|&(x, y)| self.state[x][y] != Square::Empty)
+            .fold(0, |hash, (x, y)| hash ^ Board::zobrist_key(symmetry.apply((x, y)), &self.state[x][y]))
+    }
+
+    // Smallest hash of the 8 symmetric boards, along with the symmetry
+    // mapping this board to the one having it.
+    pub fn canonical(&self) -> (u64, Symmetry) {
+        SYMMETRIES.iter()
+            .map(|&s| (self.symmetric_hash(s), s))
+            .min_by_key(|&(hash, _)| hash)
+            .unwrap()
+    }
+
+    pub fn canonical_hash(&self) -> u64 {
+        self.canonical().0
+    }
+}
@@ -0,0 +1,191 @@
+extern crate rand;
+extern crate time;
+
+use board::{Board, Move, Square};
+use eval::Evaluator;
+use minimax::{root_moves, SearchContext};
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use self::rand::Rng;
+use self::time::PreciseTime;
+
+// Book shipped with the game, built with `gomoku-book --search`.
+pub const DEFAULT_BOOK: &'static str = include_str!("../../resources/book.txt");
+
+// Moves known for the positions of the openings, keyed by the canonical
+// hash of the position: the smallest of the hashes of its 8 symmetric
+// positions. Moves are stored in the frame giving that hash.
+#[derive(Clone, Debug)]
+pub struct Book
+{
+    entries: HashMap<u64, Vec<((usize, usize), u32)>>,
+}
+
+impl Book
+{
+    pub fn new() -> Book {
+        Book { entries: HashMap::new() }
+    }
+
+    pub fn load(path: &Path) -> Result<Book, String> {
+        let mut data = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut data))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Book::from_text(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // Reads `<hash> <x> <y> <weight>` lines, the hash in hexadecimal, '#'
+    // starting a comment.
+    pub fn from_text(text: &str) -> Result<Book, String> {
+        let mut book = Book::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let invalid = || format!("line {}: expected `<hash> <x> <y> <weight>`", n + 1);
+            if fields.len() != 4 {
+                return Err(invalid());
+            }
+            let hash = u64::from_str_radix(fields[0], 16).map_err(|_| invalid())?;
+            let x = fields[1].parse::<usize>().map_err(|_| invalid())?;
+            let y = fields[2].parse::<usize>().map_err(|_| invalid())?;
+            let weight = fields[3].parse().map_err(|_| invalid())?;
+            if x >= 19 || y >= 19 {
+                return Err(invalid());
+            }
+            book.add_canonical(hash, (x, y), weight);
+        }
+        Ok(book)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut hashes = self.entries.keys().collect::<Vec<_>>();
+        hashes.sort();
+        hashes.into_iter()
+            .flat_map(|hash| self.entries[hash].iter().map(move |&((x, y), weight)| format!("{:016x} {} {} {}\n", hash, x, y, weight)))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn add_canonical(&mut self, hash: u64, pos: (usize, usize), weight: u32) {
+        let moves = self.entries.entry(hash).or_insert_with(Vec::new);
+        match moves.iter().position(|&(p, _)| p == pos) {
+            Some(i) => moves[i].1 += weight,
+            None => moves.push((pos, weight)),
+        }
+    }
+
+    // Adds `weight` to the move `pos` of `board`.
+    pub fn add(&mut self, board: &Board, pos: (usize, usize), weight: u32) {
+        let (hash, symmetry) = board.canonical();
+        self.add_canonical(hash, symmetry.apply(pos), weight);
+    }
+
+    // Book moves of `board` with their weight, in the frame of `board`.
+    pub fn moves(&self, board: &Board) -> Vec<((usize, usize), u32)> {
+        let (hash, symmetry) = board.canonical();
+        self.entries.get(&hash)
+            .map(|moves| moves.iter().map(|&(pos, weight)| (symmetry.inverse().apply(pos), weight)).collect())
+            .unwrap_or(vec![])
+    }
+
+    // Picks a legal book move at random, proportionally to the weights.
+    pub fn choose(&self, board: &Board, color: &Square) -> Option<(usize, usize)> {
+        let moves = self.moves(board).into_iter()
+            .filter(|&(pos, _)| match board.play_at(Some(pos), color, PreciseTime::now(), true) {
+                Move::Legal(_, _, _, _) => true,
+                _ => false,
+            })
+            .collect::<Vec<_>>();
+        let total = moves.iter().fold(0, |acc, &(_, weight)| acc + weight);
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rand::thread_rng().gen_range(0, total);
+        for (pos, weight) in moves {
+            if pick < weight {
+                return Some(pos);
+            }
+            pick -= weight;
+        }
+        None
+    }
+
+    // Adds the first `plies` moves of a game, Black playing first. Moves of
+    // the winner, when known, weigh twice as much.
+    pub fn add_game(&mut self, moves: &[(usize, usize)], winner: Option<Square>, plies: usize) -> Result<(), String> {
+        let mut board = Board::new();
+        let mut color = Square::Black;
+        for (n, &pos) in moves.iter().take(plies).enumerate() {
+            let weight = if winner == Some(color.clone()) { 2 } else { 1 };
+            self.add(&board, pos, weight);
+            board = match board.play_at(Some(pos), &color, PreciseTime::now(), true) {
+                Move::Legal(child, _, _, _) => child,
+                _ => return Err(format!("illegal move {} at ({}, {})", n + 1, pos.0, pos.1)),
+            };
+            color = color.opposite();
+        }
+        Ok(())
+    }
+
+    // Searches every position reached by the book moves up to `plies`
+    // moves deep, adding the `width` best moves of each, searched to
+    // `depth` within `time_limit` milliseconds. Better moves get a larger
+    // weight.
+    pub fn add_searches(&mut self, plies: usize, width: usize, depth: usize, time_limit: i64, evaluator: &Evaluator) {
+        let mut positions = vec![(Board::new(), Square::Black)];
+        for _ in 0..plies {
+            let mut next = Vec::new();
+            let mut searched = HashSet::new();
+            for (board, color) in positions {
+                // Symmetric positions share their book moves
+                if !searched.insert(board.canonical_hash()) {
+                    continue;
+                }
+                let mut ctx = SearchContext::new(PreciseTime::now(), time_limit, HashMap::new());
+                let mut moves = match root_moves(&board, depth, &color, &mut ctx, evaluator) {
+                    Some(moves) => moves,
+                    None => continue,
+                };
+                moves.sort_by_key(|&(_, score)| -(score as i64));
+                for (rank, &(pos, _)) in moves.iter().take(width).enumerate() {
+                    self.add(&board, pos, (width - rank) as u32);
+                    if let Move::Legal(child, _, _, _) = board.play_at(Some(pos), &color, PreciseTime::now(), true) {
+                        next.push((child, color.opposite()));
+                    }
+                }
+            }
+            positions = next;
+        }
+    }
+}
+
+// Reads a game as moves `x,y` separated by spaces, optionally followed by
+// the winner, `B` or `W`.
+pub fn parse_game(line: &str) -> Result<(Vec<(usize, usize)>, Option<Square>), String> {
+    let mut moves = Vec::new();
+    let mut winner = None;
+    for field in line.split_whitespace() {
+        match field {
+            "B" => winner = Some(Square::Black),
+            "W" => winner = Some(Square::White),
+            _ => {
+                let mut coords = field.split(',').map(|c| c.parse::<usize>());
+                match (coords.next(), coords.next(), coords.next()) {
+                    (Some(Ok(x)), Some(Ok(y)), None) if x < 19 && y < 19 => moves.push((x, y)),
+                    _ => return Err(format!("invalid move `{}`", field)),
+                }
+            },
+        }
+    }
+    Ok((moves, winner))
+}
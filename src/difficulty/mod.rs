@@ -0,0 +1,114 @@
+extern crate rand;
+
+use minimax::{QUIESCENCE_DEPTH, TIME_LIMIT};
+use solver;
+
+use self::rand::Rng;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Difficulty
+{
+    Beginner,
+    Easy,
+    Medium,
+    Hard,
+    Master,
+}
+
+pub const DIFFICULTIES: [(&'static str, Difficulty); 5] = [
+    ("beginner", Difficulty::Beginner),
+    ("easy", Difficulty::Easy),
+    ("medium", Difficulty::Medium),
+    ("hard", Difficulty::Hard),
+    ("master", Difficulty::Master),
+];
+
+// Search settings of a difficulty.
+#[derive(Clone, Debug)]
+pub struct Level
+{
+    pub max_depth: usize,
+    // In milliseconds
+    pub time_limit: i64,
+    // Moves scoring less than this below the best one are never played, the
+    // others are picked at random. The search plays the best move when 0.
+    pub margin: i32,
+    // Budget of the forced win solver run before the search, 0 to skip it
+    pub solver_nodes: usize,
+    // Forcing moves searched past `max_depth`, 0 to evaluate statically
+    pub quiescence_depth: usize,
+    // Patterns of `EVAL_PATTERNS` the evaluation ignores, for both colors
+    pub blind: &'static [&'static str],
+}
+
+impl Difficulty
+{
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        DIFFICULTIES.iter().find(|&&(n, _)| n == name).map(|&(_, difficulty)| difficulty)
+    }
+
+    pub fn name(&self) -> &'static str {
+        DIFFICULTIES.iter().find(|&&(_, d)| d == *self).unwrap().0
+    }
+
+    pub fn level(&self) -> Level {
+        match *self {
+            Difficulty::Beginner => Level {
+                max_depth: 1,
+                time_limit: 100,
+                margin: 2000,
+                solver_nodes: 0,
+                quiescence_depth: 0,
+                blind: &["xxx-x", "x-xxx", "xx-xx", "-x-xx", "xx-x-"],
+            },
+            Difficulty::Easy => Level {
+                max_depth: 2,
+                time_limit: 200,
+                margin: 400,
+                solver_nodes: 0,
+                quiescence_depth: 0,
+                blind: &["-x-xx", "xx-x-"],
+            },
+            Difficulty::Medium => Level {
+                max_depth: 4,
+                time_limit: 300,
+                margin: 100,
+                solver_nodes: solver::ROOT_NODES / 3,
+                quiescence_depth: QUIESCENCE_DEPTH / 2,
+                blind: &[],
+            },
+            Difficulty::Hard => Level {
+                max_depth: 12,
+                time_limit: TIME_LIMIT,
+                margin: 0,
+                solver_nodes: solver::ROOT_NODES,
+                quiescence_depth: QUIESCENCE_DEPTH,
+                blind: &[],
+            },
+            Difficulty::Master => Level {
+                max_depth: 12,
+                time_limit: 2 * TIME_LIMIT,
+                margin: 0,
+                solver_nodes: 3 * solver::ROOT_NODES,
+                quiescence_depth: 2 * QUIESCENCE_DEPTH,
+                blind: &[],
+            },
+        }
+    }
+}
+
+impl Level
+{
+    // Picks one of the moves within `margin` of the best score.
+    pub fn choose(&self, moves: &[((usize, usize), i32)]) -> Option<(usize, usize)> {
+        let best = match moves.iter().map(|&(_, score)| score).max() {
+            Some(best) => best,
+            None => return None,
+        };
+        let candidates = moves.iter()
+            .filter(|&&(_, score)| score >= best.saturating_sub(self.margin))
+            .collect::<Vec<_>>();
+        let mut rng = rand::thread_rng();
+        Some(candidates[rng.gen_range(0, candidates.len())].0)
+    }
+}
@@ -0,0 +1,137 @@
+use board::{Board, Explanation, Square, Weights, EVAL_PATTERNS, MAX_EVALUATION};
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+pub trait Evaluator
+{
+    // Score of `board` for `player`, `current_player` being the one to move.
+    fn evaluate(&self, board: &Board, player: &Square, current_player: &Square) -> i32;
+
+    // Detail of the score, for evaluators built on `Board::check_patterns`.
+    fn explain(&self, _board: &Board, _player: &Square, _current_player: &Square) -> Option<Explanation> {
+        None
+    }
+
+    // Same evaluator ignoring the given patterns of `EVAL_PATTERNS`, for
+    // evaluators built on them.
+    fn blinded(&self, _patterns: &[&str]) -> Option<Arc<Evaluator + Send + Sync>> {
+        None
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PatternEvaluator
+{
+    pub weights: Weights,
+}
+
+// Weight files shipped with the game.
+pub const PERSONALITIES: [(&'static str, &'static str); 3] = [
+    ("default", include_str!("../../resources/weights/default.cfg")),
+    ("aggressive", include_str!("../../resources/weights/aggressive.cfg")),
+    ("defensive", include_str!("../../resources/weights/defensive.cfg")),
+];
+
+impl Evaluator for PatternEvaluator
+{
+    fn evaluate(&self, board: &Board, player: &Square, current_player: &Square) -> i32 {
+        board.check_patterns(player, current_player, &self.weights)
+    }
+
+    fn explain(&self, board: &Board, player: &Square, current_player: &Square) -> Option<Explanation> {
+        Some(board.explain_evaluation(player, current_player, &self.weights))
+    }
+
+    fn blinded(&self, patterns: &[&str]) -> Option<Arc<Evaluator + Send + Sync>> {
+        let mut blinded = self.clone();
+        for (i, &(pattern, _)) in EVAL_PATTERNS.iter().enumerate() {
+            if patterns.contains(&pattern) {
+                blinded.weights.patterns[i] = 0;
+            }
+        }
+        Some(Arc::new(blinded))
+    }
+}
+
+impl PatternEvaluator
+{
+    pub fn new() -> PatternEvaluator {
+        PatternEvaluator { weights: Weights::new() }
+    }
+
+    pub fn personality(name: &str) -> Option<PatternEvaluator> {
+        PERSONALITIES.iter()
+            .find(|&&(personality, _)| personality == name)
+            .and_then(|&(_, config)| PatternEvaluator::from_config(config).ok())
+    }
+
+    pub fn load(path: &Path) -> Result<PatternEvaluator, String> {
+        let mut config = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut config))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        PatternEvaluator::from_config(&config)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // Reads `key = value` lines, where keys are either the patterns of
+    // `EVAL_PATTERNS` or the other fields of `Weights`. Missing keys keep
+    // their default value, '#' starts a comment.
+    // Values that could make the evaluation overflow or reach the win and
+    // loss scores of the search are rejected.
+    pub fn from_config(config: &str) -> Result<PatternEvaluator, String> {
+        let mut weights = Weights::new();
+        for (n, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=').map(|s| s.trim());
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => return Err(format!("line {}: expected `key = value`", n + 1)),
+            };
+            let invalid = || format!("line {}: invalid value `{}` for `{}`", n + 1, value, key);
+            match key {
+                "waiting_factor" => weights.waiting_factor = match value.parse::<f64>() {
+                    Ok(factor) if factor >= 0.0 && factor <= 1.0 => factor,
+                    _ => return Err(invalid()),
+                },
+                "capture_win" => weights.capture_win = value.parse().map_err(|_| invalid())?,
+                "capture_base" => weights.capture_base = value.parse().map_err(|_| invalid())?,
+                // 9, the most captured stones before the win, to a larger
+                // power overflows
+                "capture_exponent" => weights.capture_exponent = match value.parse() {
+                    Ok(exponent) if exponent <= 9 => exponent,
+                    _ => return Err(invalid()),
+                },
+                _ => match EVAL_PATTERNS.iter().position(|&(pattern, _)| pattern == key) {
+                    Some(i) => weights.patterns[i] = value.parse().map_err(|_| invalid())?,
+                    None => return Err(format!("line {}: unknown key `{}`", n + 1, key)),
+                },
+            }
+        }
+        if weights.max_evaluation() > MAX_EVALUATION {
+            return Err(format!("scores too large, the evaluation could reach {} while at most {} is allowed",
+                               weights.max_evaluation(), MAX_EVALUATION));
+        }
+        Ok(PatternEvaluator { weights: weights })
+    }
+
+    pub fn to_config(&self) -> String {
+        let mut config = String::from("# Pattern scores, 'x' is the evaluated color, 'y' its opponent\n");
+        for (i, &(pattern, _)) in EVAL_PATTERNS.iter().enumerate() {
+            config.push_str(&format!("{} = {}\n", pattern, self.weights.patterns[i]));
+        }
+        config.push_str("\n# Factor applied to the scores of the player who is not to move\n");
+        config.push_str(&format!("waiting_factor = {}\n", self.weights.waiting_factor));
+        config.push_str("\n# Captured stones n are worth n ^ capture_exponent + capture_base\n");
+        config.push_str(&format!("capture_win = {}\n", self.weights.capture_win));
+        config.push_str(&format!("capture_base = {}\n", self.weights.capture_base));
+        config.push_str(&format!("capture_exponent = {}\n", self.weights.capture_exponent));
+        config
+    }
+}
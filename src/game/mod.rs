@@ -1,13 +1,21 @@
 extern crate rand;
 extern crate time;
 
-use board::{Board, Move, Square};
+use board::{Board, BoardState, Explanation, Move, Rules, Square};
+use book::Book;
+use difficulty::{Difficulty, Level};
+use eval::Evaluator;
+use mcts::mcts;
 use minimax::TTEntry;
-use minimax::minimax;
+use minimax::{minimax, principal_variation, root_moves, SearchContext};
+use solver;
 
 use std::i32;
+use std::i64;
 use std::collections::HashMap;
-use std::sync::mpsc;
+use std::mem;
+use std::sync::{mpsc, Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Receiver};
 use std::thread;
 use self::rand::{Rng};
@@ -18,8 +26,47 @@ pub struct Game {
     players: Vec<Player>,
     pub current_player: Player,
     pub last_move: Option<Move>,
-    receiver: Option<Receiver<AIDecision>>,
-    map: HashMap<u64, TTEntry>,
+    // Legal moves played since the start of the game
+    pub moves: Vec<((usize, usize), Square)>,
+    // Stones removed by the last move
+    pub last_captures: Vec<((usize, usize), Square)>,
+    // Evaluation for Black of the position after each move of `moves`
+    pub scores: Vec<i32>,
+    receiver: Option<Receiver<AIMessage>>,
+    // Last progress sent by the search of the AI
+    pub progress: Option<SearchProgress>,
+    // Stops the search sending to `receiver`
+    cancel: Arc<AtomicBool>,
+    // Transposition table of each color, see `map_index`. Their scores are
+    // from the side of the player searching, so the players cannot share one.
+    maps: [HashMap<u64, TTEntry>; 2],
+    evaluator: Arc<Evaluator + Send + Sync>,
+    difficulty: Difficulty,
+    book: Arc<Book>,
+    pondering: bool,
+    ponder: Option<Ponder>,
+    // Start of the ponder search `receiver` waits for, after a correct guess
+    ponder_hit: Option<PreciseTime>,
+    // Moves taken back, the next one to redo last
+    redo: Vec<(usize, usize)>,
+    paused: bool,
+    // Search of the hint for the player to move, and its result
+    hint_search: Option<(Receiver<AIMessage>, Arc<AtomicBool>)>,
+    pub hint: Option<(usize, usize)>,
+    // Scores of the candidate moves of a human player to move, refined at
+    // each depth searched while `heat_map` is on
+    heat_map: bool,
+    heat_search: Option<(Receiver<Vec<((usize, usize), i32)>>, Arc<AtomicBool>)>,
+    pub heat: Vec<((usize, usize), i32)>,
+}
+
+// Search of the position after the expected reply of the opponent, run
+// during the turn of a human player.
+struct Ponder {
+    prediction: (usize, usize),
+    receiver: Receiver<AIMessage>,
+    cancel: Arc<AtomicBool>,
+    start: PreciseTime,
 }
 
 struct AIDecision {
@@ -28,84 +75,305 @@ struct AIDecision {
     start: PreciseTime,
 }
 
+// Result of each iteration of the alpha-beta search: the depth searched and
+// the line expected from the best move found.
+#[derive(Clone, Debug)]
+pub struct SearchProgress {
+    pub depth: usize,
+    pub variation: Vec<(usize, usize)>,
+    // Score of the best move for the player searching
+    pub score: i32,
+}
+
+enum AIMessage {
+    Progress(SearchProgress),
+    Decision(AIDecision),
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum Engine {
+    AlphaBeta,
+    Mcts,
+}
+
 #[derive(PartialEq, Clone, Debug)]
 pub struct Player {
     pub color: Square,
-    pub is_ai: bool,
+    pub ai: Option<Engine>,
+}
+
+impl Player {
+    pub fn is_ai(&self) -> bool {
+        self.ai.is_some()
+    }
 }
 
 impl Game {
-    pub fn new(ai_player: bool) -> Self {
+    // Each player is either human (`None`) or played by the given engine.
+    // The color of the first player is drawn at random when not given.
+    pub fn new(first: Option<Engine>, second: Option<Engine>, first_color: Option<Square>, rules: Rules, evaluator: Arc<Evaluator + Send + Sync>, difficulty: Difficulty, book: Arc<Book>) -> Self {
         let mut rng = rand::thread_rng();
-        Board::init_zobrist_array();
-        let toss: u8 = rng.gen_range(0, 2);
+        let toss: u8 = match first_color {
+            Some(Square::Black) => 0,
+            Some(Square::White) => 1,
+            _ => rng.gen_range(0, 2),
+        };
         let player_1 = Player {
             color: if toss == 0 { Square::Black } else { Square::White },
-            is_ai: ai_player
+            ai: first,
         };
         let player_2 = Player {
             color: if toss == 0 { Square::White } else { Square::Black },
-            is_ai: false,
+            ai: second,
         };
         let mut game = Game {
-            board: Board::new(),
+            board: Board::with_rules(rules),
             players: vec![player_1.clone(), player_2.clone()],
             current_player: if toss == 0 { player_1 } else { player_2 },
             last_move: None,
+            moves: vec![],
+            last_captures: vec![],
+            scores: vec![],
             receiver: None,
-            map: HashMap::new(),
+            progress: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+            maps: [HashMap::new(), HashMap::new()],
+            evaluator: evaluator,
+            difficulty: difficulty,
+            book: book,
+            pondering: false,
+            ponder: None,
+            ponder_hit: None,
+            redo: vec![],
+            paused: false,
+            hint_search: None,
+            hint: None,
+            heat_map: false,
+            heat_search: None,
+            heat: vec![],
         };
-        if game.current_player.is_ai {
+        if game.current_player.is_ai() {
             game.ai_move()
         };
         game
     }
 
-    fn get_input_ai(board: &Board, player: &Square, ttmap: &mut HashMap<u64, TTEntry>, now: PreciseTime) -> Option<(usize, usize)> {
+    fn get_input_ai(board: &Board, player: &Square, ttmap: &mut HashMap<u64, TTEntry>, now: PreciseTime, evaluator: &Evaluator, level: &Level, cancel: Arc<AtomicBool>, progress: &Fn(SearchProgress)) -> Option<(usize, usize)> {
+        if level.solver_nodes > 0 {
+            if let Some(sequence) = solver::solve_within(board, player, level.solver_nodes, &cancel) {
+                if let Move::Legal(_, _, _, _) = board.play_at(Some(sequence[0]), player, now, false) {
+                    return Some(sequence[0]);
+                }
+            }
+        }
         let mut prev_value: Option<(usize, usize)> = None;
-        for depth in 1..13 {
-            let value = minimax(board, depth, i32::MIN, i32::MAX, true, None, player, now, ttmap).pos;
-            if value == None { println!("Maximum depth in imparted time: {}", depth); break; }
-            else { prev_value = value; }
+        let mut ctx = SearchContext::new(now, level.time_limit, mem::replace(ttmap, HashMap::new()));
+        ctx.cancel = cancel;
+        ctx.quiescence_depth = level.quiescence_depth;
+        for depth in 1..level.max_depth + 1 {
+            let decision = if level.margin > 0 {
+                root_moves(board, depth, player, &mut ctx, evaluator)
+                    .and_then(|moves| level.choose(&moves).and_then(|pos| moves.into_iter().find(|&(p, _)| p == pos)))
+            } else {
+                let decision = minimax(board, depth, i32::MIN, i32::MAX, true, None, player, &mut ctx, evaluator);
+                decision.pos.map(|pos| (pos, decision.score))
+            };
+            let (pos, score) = match decision {
+                Some(decision) => decision,
+                None => break,
+            };
+            prev_value = Some(pos);
+            progress(SearchProgress { depth: depth, variation: principal_variation(board, player, pos, &ctx.ttmap, depth), score: score });
         }
+        *ttmap = ctx.ttmap;
         prev_value
     }
 
     pub fn update(&mut self)
     {
-        let mut received = false;
-        let mut player_move = Move::Illegal;
+        let mut received = None;
         if let Some(ref receiver) = self.receiver {
-            if let Ok(decision) = receiver.try_recv() {
-                received = true;
-                player_move = self.board.play_at(decision.pos, &self.current_player.color, decision.start, true);
-                self.map = decision.map.clone();
+            for message in receiver.try_iter() {
+                match message {
+                    AIMessage::Progress(progress) => self.progress = Some(progress),
+                    AIMessage::Decision(decision) => {
+                        received = Some(decision);
+                        break;
+                    },
+                }
             }
         }
-        if received {
-            self.apply_move(player_move);
+        if let Some(decision) = received {
+            self.maps[Game::map_index(&self.current_player.color)] = decision.map;
+            self.receiver = None;
+            self.progress = None;
+            // Ponder searches are played when they lasted at least as long as
+            // a move, otherwise the move is searched again with the filled
+            // transposition table
+            let start = match self.ponder_hit.take() {
+                Some(start) if decision.pos.is_none()
+                    || start.to(PreciseTime::now()).num_milliseconds() < self.difficulty.level().time_limit => None,
+                Some(_) => Some(PreciseTime::now()),
+                None => Some(decision.start),
+            };
+            match start {
+                Some(start) => {
+                    let player_move = self.board.play_at(decision.pos, &self.current_player.color, start, true);
+                    self.apply_move(player_move);
+                },
+                None => self.start_search(),
+            }
+        }
+        let hint = match self.hint_search {
+            Some((ref receiver, _)) => receiver.try_iter().filter_map(|message| match message {
+                AIMessage::Decision(decision) => Some(decision),
+                AIMessage::Progress(_) => None,
+            }).next(),
+            None => None,
+        };
+        if let Some(decision) = hint {
+            self.hint = decision.pos;
+            self.hint_search = None;
+        }
+        if let Some((ref receiver, _)) = self.heat_search {
+            if let Some(heat) = receiver.try_iter().last() {
+                self.heat = heat;
+            }
         }
     }
 
-    fn ai_move(&mut self)
+    // Runs the engine of `player` on `board` in another thread, starting from
+    // the transposition table `map`, until its time is up or `cancel` is set.
+    fn spawn_search(&self, board: &Board, player: &Player, mut map: HashMap<u64, TTEntry>, level: Level, cancel: Arc<AtomicBool>) -> Receiver<AIMessage>
     {
         let (tx, rx) = mpsc::channel();
-        self.receiver = Some(rx);
-        let tx = tx.clone();
-        let board = self.board.clone();
-        let mut map = self.map.clone();
-        let color = self.current_player.color.clone();
+        let board = board.clone();
+        let color = player.color.clone();
+        let engine = player.ai.clone();
+        let evaluator = self.evaluator.blinded(level.blind).unwrap_or_else(|| self.evaluator.clone());
+        // The book only knows the openings of the standard rules
+        let book = if board.rules == Rules::standard() { self.book.clone() } else { Arc::new(Book::new()) };
         thread::spawn(move || {
             let now = PreciseTime::now();
-            let pos = Game::get_input_ai(&board, &color, &mut map, now);
-            tx.send(AIDecision { pos: pos, map: map, start: now }).unwrap();
+            // The game may not wait for this search anymore
+            let progress = |progress: SearchProgress| { let _ = tx.send(AIMessage::Progress(progress)); };
+            let pos = match book.choose(&board, &color) {
+                Some(pos) => Some(pos),
+                None => match engine {
+                    Some(Engine::Mcts) => mcts(&board, &color, now, level.time_limit, &cancel, &*evaluator),
+                    _ => Game::get_input_ai(&board, &color, &mut map, now, &*evaluator, &level, cancel, &progress),
+                },
+            };
+            let _ = tx.send(AIMessage::Decision(AIDecision { pos: pos, map: map, start: now }));
+        });
+        rx
+    }
+
+    fn ai_move(&mut self)
+    {
+        self.cancel_search();
+        if self.paused {
+            return;
+        }
+        if let Some(ponder) = self.ponder.take() {
+            ponder.cancel.store(true, Ordering::Relaxed);
+            let hit = match self.last_move {
+                Some(Move::Legal(_, pos, _, _)) => pos == ponder.prediction,
+                _ => false,
+            };
+            // On a hit, the stopped search sends its best move to `update`.
+            // On a miss it is left to stop on its own.
+            if hit {
+                self.cancel = ponder.cancel;
+                self.receiver = Some(ponder.receiver);
+                self.ponder_hit = Some(ponder.start);
+                return;
+            }
+        }
+        self.start_search();
+    }
+
+    fn start_search(&mut self)
+    {
+        self.cancel = Arc::new(AtomicBool::new(false));
+        let player = self.current_player.clone();
+        let map = self.maps[Game::map_index(&player.color)].clone();
+        self.receiver = Some(self.spawn_search(&self.board, &player, map, self.difficulty.level(), self.cancel.clone()));
+    }
+
+    // Stops the search of the AI, whose move will never be played.
+    pub fn cancel_search(&mut self)
+    {
+        self.cancel.store(true, Ordering::Relaxed);
+        self.receiver = None;
+        self.ponder_hit = None;
+        self.progress = None;
+    }
+
+    // Whether the AI is searching its move.
+    pub fn is_thinking(&self) -> bool
+    {
+        self.receiver.is_some()
+    }
+
+    // When enabled, the AI searches during the turn of a human opponent the
+    // position after the reply it expects, which is the best move stored in
+    // the transposition table. Only the alpha-beta engine fills the table.
+    pub fn set_pondering(&mut self, pondering: bool)
+    {
+        self.pondering = pondering;
+        if !pondering {
+            self.stop_pondering();
+        }
+    }
+
+    fn stop_pondering(&mut self)
+    {
+        if let Some(ponder) = self.ponder.take() {
+            ponder.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn start_pondering(&mut self)
+    {
+        if self.paused {
+            return;
+        }
+        let ai = match self.players.iter().find(|player| **player != self.current_player) {
+            Some(player) if player.is_ai() && !self.current_player.is_ai() => player.clone(),
+            _ => return,
+        };
+        let prediction = match self.maps[Game::map_index(&ai.color)].get(&self.board.hash).and_then(|entry| entry.best_move()) {
+            Some(pos) => pos,
+            None => return,
+        };
+        let board = match self.board.play_at(Some(prediction), &self.current_player.color, PreciseTime::now(), true) {
+            Move::Legal(ref board, _, _, _) if !board.is_terminal() => board.clone(),
+            _ => return,
+        };
+        let mut level = self.difficulty.level();
+        level.time_limit = i64::MAX;
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.ponder = Some(Ponder {
+            prediction: prediction,
+            receiver: self.spawn_search(&board, &ai, self.maps[Game::map_index(&ai.color)].clone(), level, cancel.clone()),
+            cancel: cancel,
+            start: PreciseTime::now(),
         });
     }
 
     pub fn apply_move(&mut self, player_move: Move)
     {
         match player_move.clone() {
-            Move::Legal(board, _, _, _) => {
+            Move::Legal(board, pos, color, _) => {
+                self.scores.push(self.evaluator.evaluate(&board, &Square::Black, &color.opposite()));
+                self.moves.push((pos, color));
+                self.redo.clear();
+                self.cancel_hint();
+                self.last_captures = (0..19).flat_map(|x| (0..19).map(move |y| (x, y)))
+                    .filter(|&(x, y)| self.board.state[x][y] != Square::Empty && board.state[x][y] == Square::Empty)
+                    .map(|(x, y)| ((x, y), self.board.state[x][y].clone()))
+                    .collect();
                 self.board = board.clone();
                 self.last_move = Some(player_move);
                 self.current_player = if self.current_player == self.players[0]
@@ -116,9 +384,16 @@ impl Game {
                 {
                     self.players[0].clone()
                 };
-                if self.current_player.is_ai {
+                self.start_heat_map();
+                if self.board.is_terminal() {
+                    return;
+                }
+                if self.current_player.is_ai() {
                     self.ai_move();
                 }
+                else if self.pondering {
+                    self.start_pondering();
+                }
             },
             _ => {
                 self.last_move = Some(player_move);
@@ -126,11 +401,282 @@ impl Game {
         }
     }
 
+    // Stones of the winning alignments, or of the fives waiting to be broken,
+    // one run per direction.
+    pub fn winning_lines(&self) -> Vec<Vec<(usize, usize)>>
+    {
+        match (&self.board.game_state, &self.last_move) {
+            (&BoardState::FiveAligned(ref color, pos), _) => self.board.aligned_squares(pos, color),
+            (&BoardState::Victory(ref color), &Some(Move::Legal(_, pos, _, _))) => self.board.aligned_squares(pos, color),
+            _ => vec![],
+        }
+    }
+
+    // Number of the move that put each stone of the board, from 1.
+    pub fn move_numbers(&self) -> Vec<((usize, usize), usize)>
+    {
+        let mut numbered = Vec::new();
+        for (i, &(pos, ref color)) in self.moves.iter().enumerate().rev() {
+            if self.board.state[pos.0][pos.1] == *color && !numbered.iter().any(|&(p, _)| p == pos) {
+                numbered.push((pos, i + 1));
+            }
+        }
+        numbered
+    }
+
+    pub fn rules(&self) -> Rules
+    {
+        self.board.rules
+    }
+
+    pub fn has_ai(&self) -> bool
+    {
+        self.players.iter().any(|player| player.is_ai())
+    }
+
+    pub fn difficulty(&self) -> Difficulty
+    {
+        self.difficulty
+    }
+
+    // The transposition tables are cleared, their scores may come from a
+    // blinded evaluation, and so are the tables of the running searches: the
+    // AI searches its move again at the new difficulty.
+    pub fn set_difficulty(&mut self, difficulty: Difficulty)
+    {
+        self.difficulty = difficulty;
+        for map in self.maps.iter_mut() {
+            map.clear();
+        }
+        self.stop_pondering();
+        if self.receiver.is_some() {
+            self.ai_move();
+        }
+    }
+
+    fn map_index(color: &Square) -> usize
+    {
+        match *color {
+            Square::White => 1,
+            _ => 0,
+        }
+    }
+
+    // Detail of the evaluation of the board for the player to move.
+    pub fn explain_evaluation(&self) -> Option<Explanation>
+    {
+        self.evaluator.explain(&self.board, &self.current_player.color, &self.current_player.color)
+    }
+
+    // Evaluation of the position for Black: the score of the running search
+    // of the AI once it has one, the evaluation of the board otherwise.
+    pub fn assessment(&self) -> i32
+    {
+        match self.progress {
+            Some(ref progress) if self.current_player.color == Square::Black => progress.score,
+            Some(ref progress) => -progress.score.max(-i32::MAX),
+            None => self.scores.last().cloned().unwrap_or(0),
+        }
+    }
+
+    // Restarts the game from `moves`, Black playing first, the players
+    // keeping their colors.
+    pub fn replay(&mut self, moves: &[(usize, usize)]) -> Result<(), String>
+    {
+        let mut board = Board::with_rules(self.board.rules);
+        let mut color = Square::Black;
+        let mut played = vec![];
+        let mut scores = vec![];
+        let mut last_move = None;
+        for (n, &pos) in moves.iter().enumerate() {
+            match board.play_at(Some(pos), &color, PreciseTime::now(), true) {
+                Move::Legal(child, _, _, duration) => {
+                    last_move = Some(Move::Legal(child.clone(), pos, color.clone(), duration));
+                    board = child;
+                },
+                _ => return Err(format!("illegal move {} at {}", n + 1, Board::notation(pos))),
+            }
+            played.push((pos, color.clone()));
+            color = color.opposite();
+            scores.push(self.evaluator.evaluate(&board, &Square::Black, &color));
+        }
+        self.cancel_search();
+        self.stop_pondering();
+        self.cancel_hint();
+        self.board = board;
+        self.moves = played;
+        self.scores = scores;
+        self.last_move = last_move;
+        self.last_captures = vec![];
+        self.current_player = self.players.iter().find(|player| player.color == color).unwrap().clone();
+        self.start_heat_map();
+        if self.current_player.is_ai() && !self.board.is_terminal() {
+            self.ai_move();
+        }
+        Ok(())
+    }
+
+    // Whether the AI plays after `plies` moves while a human waits for it.
+    fn waits_for_ai(&self, plies: usize) -> bool
+    {
+        let color = if plies % 2 == 0 { Square::Black } else { Square::White };
+        self.players.iter().any(|player| !player.is_ai())
+            && self.players.iter().any(|player| player.color == color && player.is_ai())
+    }
+
+    // Takes back the last move, along with the moves of the AI before it so
+    // that the human player is to move again.
+    pub fn undo(&mut self)
+    {
+        let mut moves = self.moves.iter().map(|&(pos, _)| pos).collect::<Vec<_>>();
+        let mut redo = self.redo.clone();
+        while let Some(pos) = moves.pop() {
+            redo.push(pos);
+            if !self.waits_for_ai(moves.len()) {
+                break;
+            }
+        }
+        if self.replay(&moves).is_ok() {
+            self.redo = redo;
+        }
+    }
+
+    // Plays again the moves taken back by `undo`.
+    pub fn redo(&mut self)
+    {
+        let mut moves = self.moves.iter().map(|&(pos, _)| pos).collect::<Vec<_>>();
+        let mut redo = self.redo.clone();
+        while let Some(pos) = redo.pop() {
+            moves.push(pos);
+            if !self.waits_for_ai(moves.len()) {
+                break;
+            }
+        }
+        if self.replay(&moves).is_ok() {
+            self.redo = redo;
+        }
+    }
+
+    // A paused AI does not search, its turn waiting for the game to resume.
+    pub fn set_paused(&mut self, paused: bool)
+    {
+        self.paused = paused;
+        if paused {
+            self.cancel_search();
+            self.stop_pondering();
+        }
+        else if self.current_player.is_ai() && self.receiver.is_none() && !self.board.is_terminal() {
+            self.ai_move();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool
+    {
+        self.paused
+    }
+
+    // Searches the best move of the player to move in the background, as the
+    // AI would on the hard difficulty. It is shown in `hint` once found.
+    pub fn request_hint(&mut self)
+    {
+        self.cancel_hint();
+        if self.board.is_terminal() || self.current_player.is_ai() {
+            return;
+        }
+        let player = Player { color: self.current_player.color.clone(), ai: Some(Engine::AlphaBeta) };
+        let cancel = Arc::new(AtomicBool::new(false));
+        // The tables of the AI hold scores from its side
+        let receiver = self.spawn_search(&self.board, &player, HashMap::new(), Difficulty::Hard.level(), cancel.clone());
+        self.hint_search = Some((receiver, cancel));
+    }
+
+    fn cancel_hint(&mut self)
+    {
+        if let Some((_, cancel)) = self.hint_search.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.hint = None;
+    }
+
+    // When enabled, every candidate move of `Board::get_plays` for a human
+    // player to move is searched in the background, deeper and deeper as
+    // the hard difficulty allows, its scores shown in `heat`.
+    pub fn set_heat_map(&mut self, heat_map: bool)
+    {
+        self.heat_map = heat_map;
+        self.start_heat_map();
+    }
+
+    fn start_heat_map(&mut self)
+    {
+        self.cancel_heat_map();
+        if !self.heat_map || self.current_player.is_ai() || self.board.is_terminal() {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let board = self.board.clone();
+        let color = self.current_player.color.clone();
+        let evaluator = self.evaluator.clone();
+        let level = Difficulty::Hard.level();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut ctx = SearchContext::new(PreciseTime::now(), level.time_limit, HashMap::new());
+        ctx.cancel = cancel.clone();
+        thread::spawn(move || {
+            for depth in 1..level.max_depth + 1 {
+                let mut moves = match root_moves(&board, depth, &color, &mut ctx, &*evaluator) {
+                    Some(moves) => moves,
+                    None => break,
+                };
+                moves.sort();
+                moves.dedup_by_key(|&mut (pos, _)| pos);
+                if tx.send(moves).is_err() {
+                    break;
+                }
+            }
+        });
+        self.heat_search = Some((rx, cancel));
+    }
+
+    fn cancel_heat_map(&mut self)
+    {
+        if let Some((_, cancel)) = self.heat_search.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.heat = vec![];
+    }
+
+    // Moves of the game as read by `book::parse_game`, with the winner.
+    pub fn to_text(&self) -> String
+    {
+        let mut fields = self.moves.iter().map(|&((x, y), _)| format!("{},{}", x, y)).collect::<Vec<_>>();
+        match self.board.game_state {
+            BoardState::Victory(Square::Black) => fields.push("B".to_string()),
+            BoardState::Victory(Square::White) => fields.push("W".to_string()),
+            _ => (),
+        }
+        fields.join(" ")
+    }
+
+    // Outcome of the move `pos` of the player to move, without playing it.
+    pub fn preview(&self, pos: (usize, usize)) -> Move
+    {
+        self.board.play_at(Some(pos), &self.current_player.color, PreciseTime::now(), false)
+    }
+
     pub fn play(&mut self, pos: Option<(usize, usize)>)
     {
-        if !self.current_player.is_ai && pos != None {
+        if !self.current_player.is_ai() && pos != None {
             let player_move = self.board.play_at(pos, &self.current_player.color, PreciseTime::now(), true);
             self.apply_move(player_move);
         }
     }
 }
+
+impl Drop for Game {
+    fn drop(&mut self) {
+        self.cancel_search();
+        self.stop_pondering();
+        self.cancel_hint();
+        self.cancel_heat_map();
+    }
+}
@@ -1,9 +1,10 @@
 use glium;
 
 use std::io::Cursor;
+use std::i32;
 
 use graphics::image;
-use graphics::{glium_text, cgmath};
+use graphics::glium_text;
 
 use graphics::glium_text::{TextSystem, FontTexture};
 
@@ -12,8 +13,46 @@ use glium::{VertexBuffer, Program, Frame};
 use glium::texture::texture2d::Texture2d;
 use glium::backend::glutin_backend::GlutinFacade;
 
-use board::{Board, Move, BoardState, Square};
+use board::{Board, Explanation, Move, BoardState, Square};
 use game::{Game};
+use graphics::{Layout, Menu, Settings, Vec2f, PANEL_ROWS};
+use graphics::time::PreciseTime;
+
+// Duration of the fading of captured stones, in milliseconds
+const FADE_TIME: i64 = 600;
+// Evaluation giving Black about three quarters of the evaluation bar
+const EVALUATION_SCALE: f64 = 1000.0;
+
+// Rows of the text panel, out of `PANEL_ROWS`: the game state from the top,
+// the detail of the evaluation, the assessment bar and its graph, and the
+// state of the AI above the bottom line.
+const INFO_ROW: f64 = 1.0;
+const EVALUATION_ROW: f64 = 13.0;
+const EVALUATION_LINES: usize = 14;
+const BAR_ROW: f64 = 28.0;
+const GRAPH_ROWS: (f64, f64) = (29.5, 34.0);
+const THINKING_ROW: f64 = 35.0;
+
+// Keys handled by the main loop, listed by the help overlay.
+pub const SHORTCUTS: [(&'static str, &'static str); 17] = [
+    ("Escape", "menu"),
+    ("Arrows", "move the cursor"),
+    ("Return or Space", "play at the cursor"),
+    ("U", "undo"),
+    ("Y", "redo"),
+    ("R", "restart"),
+    ("S", "save the game"),
+    ("H", "hint"),
+    ("P", "pause the AI"),
+    ("D", "evaluation details"),
+    ("N", "move numbers"),
+    ("C", "coordinates"),
+    ("V", "line expected by the AI"),
+    ("M", "heat map of the moves"),
+    ("1 to 5", "difficulty"),
+    ("F1", "this help"),
+    ("Escape or F1", "close"),
+];
 
 #[derive(Copy, Clone)]
 struct Vertex {
@@ -25,10 +64,38 @@ pub struct App {
     texture_grid: Texture2d,
     texture_black: Texture2d,
     texture_white: Texture2d,
+    // Single white pixel, tinted to draw plain shapes
+    texture_plain: Texture2d,
     vertex_buffer: VertexBuffer<Vertex>,
     text_system: TextSystem,
     font: FontTexture,
     program: Program,
+    // Same as `program`, multiplying the texture by a `tint` color
+    tinted_program: Program,
+    settings: Settings,
+    // Debug overlay detailing the evaluation of the board
+    pub show_evaluation: bool,
+    pub show_move_numbers: bool,
+    pub show_coordinates: bool,
+    pub show_help: bool,
+    // Faint stones of the line the AI expects while it searches
+    pub show_variation: bool,
+    // Scores of the candidate moves, from red for the worst to green for
+    // the best
+    pub show_heat_map: bool,
+    // Result of the last action, shown in the panel and the menu
+    pub message: String,
+    // Intersection under the mouse cursor
+    pub hover: Option<(usize, usize)>,
+    // Intersection selected with the arrow keys, hidden when the mouse moves
+    // over the board
+    pub cursor: Option<(usize, usize)>,
+    // Stones captured by the last move, fading out since `fade_start`
+    fading: Vec<((usize, usize), Square)>,
+    fade_start: PreciseTime,
+    fade_hash: u64,
+    // Animates the thinking indicator
+    started: PreciseTime,
 }
 
 impl App {
@@ -63,11 +130,43 @@ impl App {
         glium::Program::from_source(display, vertex_shader_src, fragment_shader_src, None).unwrap()
     }
 
+    fn init_tinted_program(display: &GlutinFacade) -> Program {
+        let vertex_shader_src = r#"
+        #version 140
+
+        in vec2 position;
+        in vec2 tex_coords;
+        out vec2 v_tex_coords;
+
+        uniform mat4 matrix;
+
+        void main() {
+            v_tex_coords = tex_coords;
+            gl_Position = matrix * vec4(position, 0.0, 1.0);
+        }
+        "#;
+        let fragment_shader_src = r#"
+        #version 140
+
+        in vec2 v_tex_coords;
+        out vec4 color;
+
+        uniform sampler2D tex;
+        uniform vec4 tint;
+
+        void main() {
+            color = texture(tex, v_tex_coords) * tint;
+        }
+        "#;
+
+        glium::Program::from_source(display, vertex_shader_src, fragment_shader_src, None).unwrap()
+    }
+
     fn init_vertex_buffer(display: &GlutinFacade) -> VertexBuffer<Vertex> {
         let vertex1 = Vertex { position: [ -1.0, 1.0 ], tex_coords: [ 0.0, 1.0 ]};
-        let vertex2 = Vertex { position: [ 0.5, 1.0], tex_coords: [ 1.0, 1.0 ]};
+        let vertex2 = Vertex { position: [ 1.0, 1.0], tex_coords: [ 1.0, 1.0 ]};
         let vertex3 = Vertex { position: [ -1.0, -1.0], tex_coords: [ 0.0, 0.0 ] };
-        let vertex4 = Vertex { position: [ 0.5, -1.0], tex_coords: [ 1.0, 0.0 ] };
+        let vertex4 = Vertex { position: [ 1.0, -1.0], tex_coords: [ 1.0, 0.0 ] };
         let shape = vec![vertex1, vertex2, vertex3, vertex4];
 
         glium::VertexBuffer::new(display, &shape).unwrap()
@@ -98,16 +197,19 @@ impl App {
     {
         self.vertex_buffer = App::init_vertex_buffer(display);
         self.program = App::init_program(display);
+        self.tinted_program = App::init_tinted_program(display);
     }
 
-    pub fn new(display: &GlutinFacade) -> Self {
+    pub fn new(display: &GlutinFacade, settings: Settings) -> Self {
         implement_vertex!(Vertex, position, tex_coords);
 
         let texture_grid = App::init_texture(display, Cursor::new(&include_bytes!("../../resources/grid-color.png")[..]));
         let texture_black = App::init_texture(display, Cursor::new(&include_bytes!("../../resources/black_stone.png")[..]));
         let texture_white = App::init_texture(display, Cursor::new(&include_bytes!("../../resources/white_stone.png")[..]));
+        let texture_plain = glium::texture::Texture2d::new(display, glium::texture::RawImage2d::from_raw_rgba(vec![255u8; 4], (1, 1))).unwrap();
         let vertex_buffer = App::init_vertex_buffer(display);
         let program = App::init_program(display);
+        let tinted_program = App::init_tinted_program(display);
         let text_system = App::init_text_system(display);
         let font = App::init_font(display);
 
@@ -115,14 +217,36 @@ impl App {
             texture_grid: texture_grid,
             texture_black: texture_black,
             texture_white: texture_white,
+            texture_plain: texture_plain,
             vertex_buffer: vertex_buffer,
             program: program,
+            tinted_program: tinted_program,
             text_system: text_system,
             font: font,
+            settings: settings,
+            show_evaluation: false,
+            show_move_numbers: false,
+            show_coordinates: true,
+            show_help: false,
+            show_variation: true,
+            show_heat_map: false,
+            message: String::new(),
+            hover: None,
+            cursor: None,
+            fading: vec![],
+            fade_start: PreciseTime::now(),
+            fade_hash: 0,
+            started: PreciseTime::now(),
         }
     }
 
-    fn draw_board(&self, board: &Board, target: &mut Frame)
+    // Geometry of the window for its current framebuffer size.
+    pub fn layout(&self, size: (u32, u32)) -> Layout
+    {
+        Layout::new(&self.settings, size)
+    }
+
+    fn draw_board(&self, board: &Board, layout: &Layout, target: &mut Frame)
     {
         let indices = glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip);
         for i in 0..19
@@ -132,12 +256,7 @@ impl App {
 
                 let create_uniforms = |tex| {
                     uniform! {
-                        matrix: [
-                            [0.035, 0.0, 0.0, 0.0],
-                            [0.0, 0.035, 0.0, 0.0],
-                            [0.0, 0.0, 0.035, 0.0],
-                            [-1.0 + (j as f32 + 1.1) * 0.075, 1.0 - (i as f32 + 1.0) * 0.1, 0.0, 1.0f32],
-                        ],
+                        matrix: layout.square_quad((j, i), 0.9),
                         tex: tex,
                     }
                 };
@@ -154,9 +273,233 @@ impl App {
         }
     }
 
-    pub fn draw_text(&self, display: &GlutinFacade, game: &Game, target: &mut Frame)
+    fn draw_tinted(&self, tex: &Texture2d, matrix: [[f32; 4]; 4], tint: [f32; 4], target: &mut Frame)
+    {
+        let uniforms = uniform! {
+            matrix: matrix,
+            tex: tex,
+            tint: tint,
+        };
+        let params = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            .. Default::default()
+        };
+        let indices = glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip);
+        target.draw(&(self.vertex_buffer), &indices, &(self.tinted_program), &uniforms, &params).unwrap();
+    }
+
+    fn stone_texture(&self, color: &Square) -> &Texture2d
+    {
+        match *color {
+            Square::Black => &self.texture_black,
+            _ => &self.texture_white,
+        }
+    }
+
+    // Marker on the last move, line through the winning stones, and the
+    // stones just captured fading out.
+    fn draw_overlays(&self, game: &Game, layout: &Layout, target: &mut Frame)
+    {
+        let elapsed = self.fade_start.to(PreciseTime::now()).num_milliseconds();
+        if elapsed < FADE_TIME {
+            let alpha = 1.0 - elapsed as f32 / FADE_TIME as f32;
+            for &(pos, ref color) in &self.fading {
+                self.draw_tinted(self.stone_texture(color), layout.square_quad(pos, 0.9), [1.0, 1.0, 1.0, alpha], target);
+            }
+        }
+
+        if let Some(Move::Legal(_, pos, _, _)) = game.last_move {
+            self.draw_tinted(&self.texture_white, layout.square_quad(pos, 0.3), [0.9, 0.1, 0.1, 1.0], target);
+        }
+
+        if let Some(pos) = game.hint {
+            self.draw_tinted(&self.texture_white, layout.square_quad(pos, 0.5), [0.1, 0.7, 0.1, 0.8], target);
+        }
+
+        for line in game.winning_lines() {
+            if let (Some(&first), Some(&last)) = (line.first(), line.last()) {
+                let matrix = layout.segment(layout.intersection(first), layout.intersection(last), layout.cell_size * 0.2);
+                self.draw_tinted(&self.texture_plain, matrix, [0.9, 0.1, 0.1, 0.6], target);
+            }
+        }
+    }
+
+    // Draws `text` centered on `center`, `size` pixels high.
+    fn draw_label(&self, text: &str, center: Vec2f, size: f64, color: (f32, f32, f32, f32), layout: &Layout, target: &mut Frame)
+    {
+        let text = glium_text::TextDisplay::new(&(self.text_system), &(self.font), text);
+        let matrix = layout.text_matrix(center.x - text.get_width() as f64 * size / 2.0, center.y, size);
+        glium_text::draw(&text, &(self.text_system), target, matrix, color);
+    }
+
+    // Draws `text` from the left of the text panel on row `row`, `size` rows high.
+    fn draw_panel_line(&self, text: &str, row: f64, size: f64, color: (f32, f32, f32, f32), layout: &Layout, target: &mut Frame)
+    {
+        let text = glium_text::TextDisplay::new(&(self.text_system), &(self.font), text);
+        let matrix = layout.text_matrix(layout.panel_edges().0, layout.panel_y(row + 0.5), size * layout.row_height());
+        glium_text::draw(&text, &(self.text_system), target, matrix, color);
+    }
+
+    // Column letters above and below the board, row numbers on its sides.
+    fn draw_coordinates(&self, layout: &Layout, target: &mut Frame)
+    {
+        let size = layout.cell_size * 0.4;
+        for i in 0..19 {
+            let label = Board::notation((i, 18 - i));
+            let (column, row) = label.split_at(1);
+            let top = layout.intersection((i, 0));
+            let bottom = layout.intersection((i, 18));
+            let left = layout.intersection((0, 18 - i));
+            let right = layout.intersection((18, 18 - i));
+            let offset = layout.cell_size * 0.6;
+            self.draw_label(column, Vec2f { x: top.x, y: top.y - offset }, size, (0.0, 0.0, 0.0, 1.0), layout, target);
+            self.draw_label(column, Vec2f { x: bottom.x, y: bottom.y + offset }, size, (0.0, 0.0, 0.0, 1.0), layout, target);
+            self.draw_label(row, Vec2f { x: left.x - offset, y: left.y }, size, (0.0, 0.0, 0.0, 1.0), layout, target);
+            self.draw_label(row, Vec2f { x: right.x + offset, y: right.y }, size, (0.0, 0.0, 0.0, 1.0), layout, target);
+        }
+    }
+
+    fn draw_move_numbers(&self, game: &Game, layout: &Layout, target: &mut Frame)
+    {
+        for (pos, number) in game.move_numbers() {
+            let color = match game.board.state[pos.0][pos.1] {
+                Square::Black => (1.0, 1.0, 1.0, 1.0),
+                _ => (0.0, 0.0, 0.0, 1.0),
+            };
+            self.draw_label(&number.to_string(), layout.intersection(pos), layout.cell_size * 0.35, color, layout, target);
+        }
+    }
+
+    fn draw_heat_map(&self, game: &Game, layout: &Layout, target: &mut Frame)
+    {
+        let (min, max) = match (game.heat.iter().map(|&(_, score)| score).min(), game.heat.iter().map(|&(_, score)| score).max()) {
+            (Some(min), Some(max)) => (min as f64, max as f64),
+            _ => return,
+        };
+        for &(pos, score) in &game.heat {
+            if game.board.state[pos.0][pos.1] != Square::Empty {
+                continue;
+            }
+            let t = if max > min { (score as f64 - min) / (max - min) } else { 1.0 } as f32;
+            self.draw_tinted(&self.texture_plain, layout.square_quad(pos, 0.8), [1.0 - t, t, 0.0, 0.45], target);
+        }
+    }
+
+    // Principal variation of the running search, numbered in the order of
+    // the moves, starting with the move of the player to move.
+    fn draw_variation(&self, game: &Game, layout: &Layout, target: &mut Frame)
+    {
+        let progress = match game.progress {
+            Some(ref progress) => progress,
+            None => return,
+        };
+        let mut color = game.current_player.color.clone();
+        for (i, &pos) in progress.variation.iter().enumerate() {
+            if game.board.state[pos.0][pos.1] == Square::Empty {
+                self.draw_tinted(self.stone_texture(&color), layout.square_quad(pos, 0.9), [1.0, 1.0, 1.0, 0.3], target);
+                let number = match color {
+                    Square::Black => (1.0, 1.0, 1.0, 0.8),
+                    _ => (0.0, 0.0, 0.0, 0.6),
+                };
+                self.draw_label(&(i + 1).to_string(), layout.intersection(pos), layout.cell_size * 0.35, number, layout, target);
+            }
+            color = color.opposite();
+        }
+    }
+
+    // Share of the evaluation bar given to Black for `score`.
+    fn black_share(score: i32) -> f64
     {
+        0.5 + (score as f64 / EVALUATION_SCALE).tanh() / 2.0
+    }
 
+    // Bar splitting the panel width between Black and White after the
+    // assessment of the position, and the evaluation after every move
+    // below it, Black's advantage upwards.
+    fn draw_assessment(&self, game: &Game, layout: &Layout, target: &mut Frame)
+    {
+        let (left, right) = layout.panel_edges();
+        let width = right - left;
+        let score = game.assessment();
+        let share = App::black_share(score);
+
+        let bar = Vec2f { x: left + width / 2.0, y: layout.panel_y(BAR_ROW + 0.5) };
+        let half = 0.45 * layout.row_height();
+        self.draw_tinted(&self.texture_plain, layout.quad(bar, Vec2f { x: width / 2.0 + 1.0, y: half + 1.0 }), [0.3, 0.3, 0.3, 1.0], target);
+        self.draw_tinted(&self.texture_plain, layout.quad(Vec2f { x: left + width * share / 2.0, y: bar.y }, Vec2f { x: width * share / 2.0, y: half }), [0.05, 0.05, 0.05, 1.0], target);
+        self.draw_tinted(&self.texture_plain, layout.quad(Vec2f { x: right - width * (1.0 - share) / 2.0, y: bar.y }, Vec2f { x: width * (1.0 - share) / 2.0, y: half }), [0.95, 0.95, 0.95, 1.0], target);
+        let label = format!("{} {:+}", if score < 0 { Square::White } else { Square::Black }, score.max(-i32::MAX).abs());
+        self.draw_label(&label, bar, half * 1.6, (0.8, 0.1, 0.1, 1.0), layout, target);
+
+        let (top, bottom) = (layout.panel_y(GRAPH_ROWS.0), layout.panel_y(GRAPH_ROWS.1));
+        let graph = Vec2f { x: bar.x, y: (top + bottom) / 2.0 };
+        self.draw_tinted(&self.texture_plain, layout.quad(graph, Vec2f { x: width / 2.0, y: (bottom - top) / 2.0 }), [0.92, 0.92, 0.92, 1.0], target);
+        self.draw_tinted(&self.texture_plain, layout.segment(Vec2f { x: left, y: graph.y }, Vec2f { x: right, y: graph.y }, 1.0), [0.6, 0.6, 0.6, 1.0], target);
+        if game.scores.len() < 2 {
+            return;
+        }
+        let step = width / (game.scores.len() - 1) as f64;
+        let points = game.scores.iter().enumerate()
+            .map(|(i, &score)| Vec2f { x: left + i as f64 * step, y: bottom - App::black_share(score) * (bottom - top) })
+            .collect::<Vec<_>>();
+        for pair in points.windows(2) {
+            self.draw_tinted(&self.texture_plain, layout.segment(pair[0], pair[1], 2.0), [0.8, 0.1, 0.1, 1.0], target);
+        }
+    }
+
+    // Items of the menu over the board, dimmed, the selected one in red.
+    fn draw_menu(&self, menu: &Menu, layout: &Layout, target: &mut Frame)
+    {
+        let center = Vec2f { x: layout.size.0 as f64 / 2.0, y: layout.size.1 as f64 / 2.0 };
+        self.draw_tinted(&self.texture_plain, layout.quad(center, center), [1.0, 1.0, 1.0, 0.85], target);
+        let spacing = Menu::spacing(layout);
+        let first = menu.item_center(layout, 0);
+        self.draw_label("Gomoku", Vec2f { x: first.x, y: first.y - 1.5 * spacing }, spacing * 0.8, (0.0, 0.0, 0.0, 1.0), layout, target);
+        let lines = menu.lines();
+        for (i, line) in lines.iter().enumerate() {
+            let color = if i == menu.selected() { (0.8, 0.1, 0.1, 1.0) } else { (0.0, 0.0, 0.0, 1.0) };
+            self.draw_label(line, menu.item_center(layout, i), spacing * 0.5, color, layout, target);
+        }
+        if !self.message.is_empty() {
+            let last = menu.item_center(layout, lines.len() - 1);
+            self.draw_label(&self.message, Vec2f { x: last.x, y: last.y + 1.5 * spacing }, spacing * 0.4, (0.4, 0.0, 0.0, 1.0), layout, target);
+        }
+    }
+
+    fn draw_help(&self, layout: &Layout, target: &mut Frame)
+    {
+        let center = Vec2f { x: layout.size.0 as f64 / 2.0, y: layout.size.1 as f64 / 2.0 };
+        self.draw_tinted(&self.texture_plain, layout.quad(center, center), [1.0, 1.0, 1.0, 0.85], target);
+        let spacing = layout.size.1 as f64 / 20.0;
+        for (i, &(key, action)) in SHORTCUTS.iter().enumerate() {
+            let y = center.y + (i as f64 - (SHORTCUTS.len() - 1) as f64 / 2.0) * spacing;
+            self.draw_label(&format!("{}: {}", key, action), Vec2f { x: center.x, y: y }, spacing * 0.5, (0.0, 0.0, 0.0, 1.0), layout, target);
+        }
+    }
+
+    // Translucent stone of the player to move at `pos`, tinted red where the
+    // move is not allowed.
+    fn draw_preview(&self, game: &Game, pos: Option<(usize, usize)>, layout: &Layout, target: &mut Frame)
+    {
+        let pos = match pos {
+            Some(pos) if !game.current_player.is_ai() && game.board.state[pos.0][pos.1] == Square::Empty => pos,
+            _ => return,
+        };
+        match game.board.game_state {
+            BoardState::InProgress | BoardState::FiveAligned(_, _) => (),
+            _ => return,
+        }
+        let tint = match game.preview(pos) {
+            Move::Legal(_, _, _, _) => [1.0, 1.0, 1.0, 0.5f32],
+            _ => [1.0, 0.2, 0.2, 0.7f32],
+        };
+        self.draw_tinted(self.stone_texture(&game.current_player.color), layout.square_quad(pos, 0.9), tint, target);
+    }
+
+    // Game state and captures, one line every two rows from the top of the
+    // text panel, and the state of the AI at its bottom.
+    pub fn draw_text(&self, game: &Game, layout: &Layout, target: &mut Frame)
+    {
         let state = match game.board.game_state {
               BoardState::Victory(Square::Black) => "Black Victory!",
               BoardState::Victory(Square::White) => "White Victory!",
@@ -167,115 +510,163 @@ impl App {
               BoardState::Draw => "Draw",
               BoardState::InProgress => "Game in progress",
         };
-        let state_text = glium_text::TextDisplay::new(&(self.text_system), &(self.font), state);
-
-        let b_capture = format!("{}{}", game.board.b_capture.to_string(), " stones taken by Black");
-        let b_capture_text = glium_text::TextDisplay::new(&(self.text_system), &(self.font), b_capture.as_str());
-
-        let w_capture = format!("{}{}", game.board.w_capture.to_string(), " stones taken by White");
-        let w_capture_text = glium_text::TextDisplay::new(&(self.text_system), &(self.font), w_capture.as_str());
-
         let last_move = match game.last_move {
             Some(Move::Illegal) => "Illegal move".to_string(),
             Some(Move::DoubleThrees) => "Double Three move".to_string(),
-            Some(Move::Legal(_, (x, y), ref color, _)) => format!("{} {} at ({}, {})", "Last move:", color, (x + 1).to_string(), (y + 1).to_string()),
+            Some(Move::Legal(_, pos, ref color, _)) => format!("{} {} at {}", "Last move:", color, Board::notation(pos)),
             Some(Move::OutOfBounds) => "Out of Bounds".to_string(),
             Some(Move::FiveNotInterrupted) => "Break the alignement!".to_string(),
             Some(Move::MoveIntoCapture) => "Cannot move into a capture".to_string(),
             Some(Move::Other(message)) => message.to_string(),
             _ => "No moves yet".to_string(),
         };
-        let last_move_text = glium_text::TextDisplay::new(&(self.text_system), &(self.font), last_move.as_str());
-
-
-
-
-        let (w, h) = display.get_framebuffer_dimensions();
-
-        let state_matrix:[[f32; 4]; 4] = cgmath::Matrix4::new(
-            0.25 / 10.0, 0.0, 0.0, 0.0,
-            0.0, 0.25 * (w as f32) / (h as f32) / 10.0, 0.0, 0.0,
-            0.0, 0.0, 1.0, 0.0,
-            0.52, 0.91, 0.0, 1.0f32,
-            ).into();
-
-        let b_capture_matrix:[[f32; 4]; 4] = cgmath::Matrix4::new(
-            0.25 / 10.0, 0.0, 0.0, 0.0,
-            0.0, 0.25 * (w as f32) / (h as f32) / 10.0, 0.0, 0.0,
-            0.0, 0.0, 1.0, 0.0,
-            0.52, 0.81, 0.0, 1.0f32,
-            ).into();
-
-        let w_capture_matrix:[[f32; 4]; 4] = cgmath::Matrix4::new(
-            0.25 / 10.0, 0.0, 0.0, 0.0,
-            0.0, 0.25 * (w as f32) / (h as f32) / 10.0, 0.0, 0.0,
-            0.0, 0.0, 1.0, 0.0,
-            0.52, 0.71, 0.0, 1.0f32,
-            ).into();
-
-        let last_move_matrix:[[f32; 4]; 4] = cgmath::Matrix4::new(
-            0.25 / 10.0, 0.0, 0.0, 0.0,
-            0.0, 0.25 * (w as f32) / (h as f32) / 10.0, 0.0, 0.0,
-            0.0, 0.0, 1.0, 0.0,
-            0.52, 0.61, 0.0, 1.0f32,
-            ).into();
-
-         let time_matrix:[[f32; 4]; 4] = cgmath::Matrix4::new(
-            0.25 / 10.0, 0.0, 0.0, 0.0,
-            0.0, 0.25 * (w as f32) / (h as f32) / 10.0, 0.0, 0.0,
-            0.0, 0.0, 1.0, 0.0,
-            0.52, 0.51, 0.0, 1.0f32,
-            ).into();
-       
-
-        glium_text::draw(&state_text, &(self.text_system), target, state_matrix, (0.0, 0.0, 0.0, 1.0));
-        glium_text::draw(&b_capture_text, &(self.text_system), target, b_capture_matrix, (0.0, 0.0, 0.0, 1.0));
-        glium_text::draw(&w_capture_text, &(self.text_system), target, w_capture_matrix, (0.0, 0.0, 0.0, 1.0));
-        glium_text::draw(&last_move_text, &(self.text_system), target, last_move_matrix, (0.0, 0.0, 0.0, 1.0));
-
-        if let Some(Move::Legal(_, _, _, time)) = game.last_move {
-            let time = format!("Last move duration: {:.2}", time.num_milliseconds() as f64 / 1000.0);
-            let time_text = glium_text::TextDisplay::new(&(self.text_system), &(self.font), time.as_str());
-            glium_text::draw(&time_text, &(self.text_system), target, time_matrix, (0.0, 0.0, 0.0, 1.0));
+        let time = match game.last_move {
+            Some(Move::Legal(_, _, _, time)) => format!("Last move duration: {:.2}", time.num_milliseconds() as f64 / 1000.0),
+            _ => String::new(),
         };
+        let difficulty = if game.has_ai() {
+            format!("Difficulty: {} (keys 1 to 5)", game.difficulty().name())
+        } else {
+            String::new()
+        };
+        let lines = [
+            state.to_string(),
+            format!("{} stones taken by Black", game.board.b_capture),
+            format!("{} stones taken by White", game.board.w_capture),
+            last_move,
+            time,
+            difficulty,
+        ];
+        for (i, line) in lines.iter().enumerate().filter(|&(_, line)| !line.is_empty()) {
+            self.draw_panel_line(line, INFO_ROW + 2.0 * i as f64, 0.8, (0.0, 0.0, 0.0, 1.0), layout, target);
+        }
+
+        if game.is_thinking() {
+            let dots = &"..."[..(self.started.to(PreciseTime::now()).num_milliseconds() / 400 % 4) as usize];
+            let thinking = match game.progress {
+                Some(ref progress) => format!("AI thinking{:<3} depth {}, best {}", dots,
+                                              progress.depth, progress.variation.first().map_or(String::new(), |&pos| Board::notation(pos))),
+                None => format!("AI thinking{}", dots),
+            };
+            self.draw_panel_line(&thinking, THINKING_ROW, 0.6, (0.0, 0.0, 0.4, 1.0), layout, target);
+        }
+
+        if game.is_paused() || !self.message.is_empty() {
+            let status = if game.is_paused() { format!("AI paused (P) {}", self.message) } else { self.message.clone() };
+            self.draw_panel_line(&status, THINKING_ROW + 1.5, 0.6, (0.4, 0.0, 0.0, 1.0), layout, target);
+        }
+
+        self.draw_panel_line("Escape: menu, F1: help", PANEL_ROWS - 1.5, 0.8, (0.0, 0.0, 0.0, 1.0), layout, target);
     }
 
+    // Marks the squares of every matched pattern with a small stone of its
+    // color, and lists the main terms of the evaluation under the game state.
+    fn draw_evaluation(&self, explanation: &Explanation, layout: &Layout, target: &mut Frame)
+    {
+        let indices = glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip);
+        for m in &explanation.patterns {
+            let tex = match m.color {
+                Square::Black => &self.texture_black,
+                _ => &self.texture_white,
+            };
+            for &(x, y) in &m.squares {
+                let corner = layout.intersection((x, y));
+                let half = layout.cell_size * 0.15;
+                let uniforms = uniform! {
+                    matrix: layout.quad(Vec2f { x: corner.x + 2.0 * half, y: corner.y + 2.0 * half }, Vec2f { x: half, y: half }),
+                    tex: tex,
+                };
+                target.draw(&(self.vertex_buffer), &indices, &(self.program), &uniforms, &Default::default()).unwrap();
+            }
+        }
+
+        let mut patterns = explanation.patterns.iter().collect::<Vec<_>>();
+        patterns.sort_by_key(|m| -m.score.abs());
+        let mut lines = vec![
+            format!("Evaluation for {}: {}", explanation.player, explanation.total),
+            format!("{} patterns: {}", explanation.player, explanation.pattern_score(&explanation.player)),
+            format!("{} patterns: {}", explanation.player.opposite(), explanation.pattern_score(&explanation.player.opposite())),
+        ];
+        lines.extend(explanation.captures.iter().map(|c| format!("{} captures: {}", c.color, c.score)));
+        lines.extend(patterns.iter().take(8).map(|m| {
+            format!("{} {} at {}: {}", m.color, m.pattern, Board::notation(m.squares[0]), m.score)
+        }));
+
+        for (i, line) in lines.iter().take(EVALUATION_LINES).enumerate() {
+            self.draw_panel_line(line, EVALUATION_ROW + i as f64, 0.6, (0.4, 0.0, 0.0, 1.0), layout, target);
+        }
+    }
 
-    pub fn on_render(&self, display: &GlutinFacade, game: &Game)
+    pub fn on_render(&mut self, display: &GlutinFacade, game: &Game, menu: &Menu)
     {
+        if game.board.hash != self.fade_hash {
+            self.fade_hash = game.board.hash;
+            self.fading = game.last_captures.clone();
+            self.fade_start = PreciseTime::now();
+        }
         let mut target = display.draw();
         target.clear_color(1.0, 1.0, 1.0, 1.0);
         let indices = glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip);
+        let layout = self.layout(display.get_framebuffer_dimensions());
 
         let uniforms = uniform! {
-            matrix: [
-                [1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [0.0, 0.0, 0.0, 1.0f32],
-            ],
+            matrix: layout.grid_quad(),
             tex: &(self.texture_grid),
         };
 
         target.draw(&(self.vertex_buffer), &indices, &(self.program), &uniforms, &Default::default()).unwrap();
-        self.draw_board(&game.board, &mut target);
-        self.draw_text(display, game, &mut target);
+        if self.show_coordinates {
+            self.draw_coordinates(&layout, &mut target);
+        }
+        self.draw_board(&game.board, &layout, &mut target);
+        if self.show_move_numbers {
+            self.draw_move_numbers(game, &layout, &mut target);
+        }
+        if self.show_heat_map {
+            self.draw_heat_map(game, &layout, &mut target);
+        }
+        if self.show_variation {
+            self.draw_variation(game, &layout, &mut target);
+        }
+        self.draw_overlays(game, &layout, &mut target);
+        if !menu.open {
+            if let Some(pos) = self.cursor {
+                self.draw_tinted(&self.texture_plain, layout.square_quad(pos, 1.0), [0.1, 0.3, 0.9, 0.35], &mut target);
+            }
+            self.draw_preview(game, self.cursor.or(self.hover), &layout, &mut target);
+        }
+        self.draw_text(game, &layout, &mut target);
+        self.draw_assessment(game, &layout, &mut target);
+        if self.show_evaluation {
+            if let Some(explanation) = game.explain_evaluation() {
+                self.draw_evaluation(&explanation, &layout, &mut target);
+            }
+        }
+        if menu.open {
+            self.draw_menu(menu, &layout, &mut target);
+        }
+        else if self.show_help {
+            self.draw_help(&layout, &mut target);
+        }
         target.finish().unwrap();
     }
 
+    pub fn on_mouse_moved(&mut self, mouse_pos: &[f64; 2], size_pixels: (u32, u32)) {
+        self.hover = self.layout(size_pixels).square_at(mouse_pos);
+        if self.hover.is_some() {
+            self.cursor = None;
+        }
+    }
+
+    // Moves the keyboard cursor, starting from the intersection under the
+    // mouse or the center of the board.
+    pub fn move_cursor(&mut self, dx: i32, dy: i32) {
+        let (x, y) = self.cursor.or(self.hover).unwrap_or((9, 9));
+        let clamp = |v: i32| if v < 0 { 0 } else if v > 18 { 18 } else { v as usize };
+        self.cursor = Some((clamp(x as i32 + dx), clamp(y as i32 + dy)));
+    }
+
     pub fn on_click(&self, mouse_pos: &[f64; 2], size_pixels: (u32, u32)) -> Option<(usize, usize)> {
-        let (spx, spy) = size_pixels;
-        let ratiox = 0.75 * spx as f64 / 20.0;
-        let ratioy = spy as f64 / 20.0;
-        let mut x = mouse_pos[0] - ratiox;
-        x = x / ratiox;
-        let mut y = mouse_pos[1] - ratioy;
-        y = y / ratioy;
-        if (x.fract().abs() < 0.3 || (x.fract() > 0.7 && x > 0.0)) && (y.fract().abs() < 0.3 || (y.fract() > 0.7 && x > 0.0)) {
-            if x < 0.0 { x = 0.0 }
-            if y < 0.0 { y = 0.0 }
-            Some((x.round() as usize, y.round() as usize))
-        }
-        else { None }
+        self.layout(size_pixels).square_at(mouse_pos)
     }
 }
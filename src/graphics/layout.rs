@@ -0,0 +1,133 @@
+use graphics::settings::{Settings, Vec2f};
+
+// Number of rows the height of the text panel is divided into.
+pub const PANEL_ROWS: f64 = 40.0;
+
+// Geometry of the window for a framebuffer size, in pixels from its top left
+// corner. The board keeps the proportions given by `Settings`, square and
+// centered in the left part of the window, and the text panel takes the
+// rest, keeping its share of the default window width.
+pub struct Layout
+{
+    pub size: (u32, u32),
+    // Top left corner of the board, margins included
+    pub board_origin: Vec2f,
+    pub board_size: Vec2f,
+    pub cell_size: f64,
+    pub margin: Vec2f,
+    // Left edge of the text panel
+    pub panel_x: f64,
+}
+
+impl Layout
+{
+    pub fn new(settings: &Settings, size: (u32, u32)) -> Layout {
+        let (w, h) = (size.0 as f64, size.1 as f64);
+        let board = settings.board_size();
+        let panel_x = w * board.x / settings.win_size.x;
+        let scale = (panel_x / board.x).min(h / board.y);
+        let board_size = Vec2f { x: board.x * scale, y: board.y * scale };
+        Layout {
+            size: size,
+            board_origin: Vec2f { x: (panel_x - board_size.x) / 2.0, y: (h - board_size.y) / 2.0 },
+            board_size: board_size,
+            cell_size: settings.cell_size * scale,
+            margin: Vec2f { x: settings.board_margin.x * scale, y: settings.board_margin.y * scale },
+            panel_x: panel_x,
+        }
+    }
+
+    // Center of the intersection `pos`.
+    pub fn intersection(&self, pos: (usize, usize)) -> Vec2f {
+        Vec2f {
+            x: self.board_origin.x + self.margin.x + pos.0 as f64 * self.cell_size,
+            y: self.board_origin.y + self.margin.y + pos.1 as f64 * self.cell_size,
+        }
+    }
+
+    // Intersection under `pixel`, when it is close enough to one.
+    pub fn square_at(&self, pixel: &[f64; 2]) -> Option<(usize, usize)> {
+        let x = (pixel[0] - self.board_origin.x - self.margin.x) / self.cell_size;
+        let y = (pixel[1] - self.board_origin.y - self.margin.y) / self.cell_size;
+        let near = |v: f64| (v - v.round()).abs() < 0.3 && v.round() >= 0.0 && v.round() <= 18.0;
+        if near(x) && near(y) {
+            Some((x.round() as usize, y.round() as usize))
+        }
+        else { None }
+    }
+
+    pub fn row_height(&self) -> f64 {
+        self.size.1 as f64 / PANEL_ROWS
+    }
+
+    // Distance from the top of the window of `row` rows of the text panel,
+    // fractions of rows included.
+    pub fn panel_y(&self, row: f64) -> f64 {
+        row * self.row_height()
+    }
+
+    // Left and right edges of the content of the text panel.
+    pub fn panel_edges(&self) -> (f64, f64) {
+        let w = self.size.0 as f64;
+        (self.panel_x + 0.01 * w, w - 0.03 * w)
+    }
+
+    pub fn gl_x(&self, x: f64) -> f32 {
+        (2.0 * x / self.size.0 as f64 - 1.0) as f32
+    }
+
+    pub fn gl_y(&self, y: f64) -> f32 {
+        (1.0 - 2.0 * y / self.size.1 as f64) as f32
+    }
+
+    // Matrix mapping the unit quad of `App` on the rectangle of the given
+    // center and half extents.
+    pub fn quad(&self, center: Vec2f, half: Vec2f) -> [[f32; 4]; 4] {
+        let sx = (2.0 * half.x / self.size.0 as f64) as f32;
+        let sy = (2.0 * half.y / self.size.1 as f64) as f32;
+        [
+            [sx, 0.0, 0.0, 0.0],
+            [0.0, sy, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [self.gl_x(center.x), self.gl_y(center.y), 0.0, 1.0f32],
+        ]
+    }
+
+    // Matrix of a text of glium_text starting at `left`, vertically centered
+    // on `y` and `size` pixels high.
+    pub fn text_matrix(&self, left: f64, y: f64, size: f64) -> [[f32; 4]; 4] {
+        let sx = (2.0 * size / self.size.0 as f64) as f32;
+        let sy = (2.0 * size / self.size.1 as f64) as f32;
+        [
+            [sx, 0.0, 0.0, 0.0],
+            [0.0, sy, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [self.gl_x(left), self.gl_y(y) - sy * 0.35, 0.0, 1.0f32],
+        ]
+    }
+
+    // Segment of the given width from `from` to `to`, as a rotated quad.
+    pub fn segment(&self, from: Vec2f, to: Vec2f, width: f64) -> [[f32; 4]; 4] {
+        let (dx, dy) = ((to.x - from.x) / 2.0, (to.y - from.y) / 2.0);
+        let length = (dx * dx + dy * dy).sqrt().max(1.0);
+        let (nx, ny) = (-dy / length * width / 2.0, dx / length * width / 2.0);
+        let (w, h) = (self.size.0 as f64, self.size.1 as f64);
+        [
+            [(2.0 * dx / w) as f32, (-2.0 * dy / h) as f32, 0.0, 0.0],
+            [(2.0 * nx / w) as f32, (-2.0 * ny / h) as f32, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [self.gl_x(from.x + dx), self.gl_y(from.y + dy), 0.0, 1.0f32],
+        ]
+    }
+
+    // Square of side `ratio` cells centered on the intersection `pos`.
+    pub fn square_quad(&self, pos: (usize, usize), ratio: f64) -> [[f32; 4]; 4] {
+        let half = self.cell_size * ratio / 2.0;
+        self.quad(self.intersection(pos), Vec2f { x: half, y: half })
+    }
+
+    // The grid texture has 20 cells, its lines one cell from its edges.
+    pub fn grid_quad(&self) -> [[f32; 4]; 4] {
+        self.square_quad((9, 9), 20.0)
+    }
+}
@@ -0,0 +1,205 @@
+use board::{Rules, Square, VARIANTS};
+use difficulty::{Difficulty, DIFFICULTIES};
+use graphics::{Layout, Vec2f};
+use graphics::glutin::VirtualKeyCode;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mode
+{
+    TwoPlayers,
+    VsAi,
+    AiVsAi,
+}
+
+pub const MODES: [(&'static str, Mode); 3] = [
+    ("two_players", Mode::TwoPlayers),
+    ("vs_ai", Mode::VsAi),
+    ("ai_vs_ai", Mode::AiVsAi),
+];
+
+impl Mode
+{
+    pub fn from_name(name: &str) -> Option<Mode> {
+        MODES.iter().find(|&&(n, _)| n == name).map(|&(_, mode)| mode)
+    }
+}
+
+// What the main loop has to do after a choice in the menu.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuAction
+{
+    NewGame,
+    Save,
+    Load,
+    Quit,
+    SetDifficulty(Difficulty),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Item
+{
+    Resume,
+    NewGame,
+    Mode,
+    Color,
+    Rules,
+    Difficulty,
+    Save,
+    Load,
+    Quit,
+}
+
+const ITEMS: [Item; 9] = [
+    Item::Resume, Item::NewGame, Item::Mode, Item::Color, Item::Rules,
+    Item::Difficulty, Item::Save, Item::Load, Item::Quit,
+];
+
+const COLORS: [Option<Square>; 3] = [None, Some(Square::Black), Some(Square::White)];
+
+// Menu drawn over the board, choosing the settings of the next game.
+pub struct Menu
+{
+    pub open: bool,
+    selected: usize,
+    pub mode: Mode,
+    // Color of the human player against the AI, of the first player
+    // otherwise. Drawn at random when `None`.
+    pub color: Option<Square>,
+    pub rules: Rules,
+    pub difficulty: Difficulty,
+}
+
+impl Menu
+{
+    pub fn new(mode: Mode, rules: Rules, difficulty: Difficulty) -> Menu {
+        Menu {
+            open: false,
+            selected: 0,
+            mode: mode,
+            color: None,
+            rules: rules,
+            difficulty: difficulty,
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        ITEMS.iter().map(|item| match *item {
+            Item::Resume => "Resume".to_string(),
+            Item::NewGame => "New game".to_string(),
+            Item::Mode => format!("Mode: < {} >", match self.mode {
+                Mode::TwoPlayers => "two players",
+                Mode::VsAi => "against the AI",
+                Mode::AiVsAi => "AI against AI",
+            }),
+            Item::Color => format!("{}: < {} >",
+                                   if self.mode == Mode::VsAi { "Your color" } else { "First player" },
+                                   self.color.as_ref().map_or("random".to_string(), |color| color.to_string())),
+            Item::Rules => format!("Rules: < {} >", self.rules.name().replace('_', " ")),
+            Item::Difficulty => format!("Difficulty: < {} >", self.difficulty.name()),
+            Item::Save => "Save game".to_string(),
+            Item::Load => "Load game".to_string(),
+            Item::Quit => "Quit".to_string(),
+        }).collect()
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    // Center of the line of the item `i`, the items being centered on the
+    // window.
+    pub fn item_center(&self, layout: &Layout, i: usize) -> Vec2f {
+        let spacing = Menu::spacing(layout);
+        Vec2f {
+            x: layout.size.0 as f64 / 2.0,
+            y: layout.size.1 as f64 / 2.0 + (i as f64 - (ITEMS.len() - 1) as f64 / 2.0) * spacing,
+        }
+    }
+
+    pub fn spacing(layout: &Layout) -> f64 {
+        layout.size.1 as f64 / 14.0
+    }
+
+    fn item_at(&self, layout: &Layout, pixel: &[f64; 2]) -> Option<usize> {
+        let spacing = Menu::spacing(layout);
+        (0..ITEMS.len()).find(|&i| {
+            let center = self.item_center(layout, i);
+            (pixel[1] - center.y).abs() < spacing / 2.0 && (pixel[0] - center.x).abs() < layout.size.0 as f64 / 4.0
+        })
+    }
+
+    // Up and down select an item, left and right change the settings, and
+    // Return activates the selection.
+    pub fn on_key(&mut self, key: VirtualKeyCode) -> Option<MenuAction> {
+        match key {
+            VirtualKeyCode::Up => {
+                self.selected = (self.selected + ITEMS.len() - 1) % ITEMS.len();
+                None
+            },
+            VirtualKeyCode::Down => {
+                self.selected = (self.selected + 1) % ITEMS.len();
+                None
+            },
+            VirtualKeyCode::Left => self.activate(-1),
+            VirtualKeyCode::Right | VirtualKeyCode::Return => self.activate(1),
+            _ => None,
+        }
+    }
+
+    pub fn on_mouse_moved(&mut self, layout: &Layout, pixel: &[f64; 2]) {
+        if let Some(i) = self.item_at(layout, pixel) {
+            self.selected = i;
+        }
+    }
+
+    pub fn on_click(&mut self, layout: &Layout, pixel: &[f64; 2]) -> Option<MenuAction> {
+        match self.item_at(layout, pixel) {
+            Some(i) => {
+                self.selected = i;
+                self.activate(1)
+            },
+            None => None,
+        }
+    }
+
+    // Runs the selected item, settings moving by `step` among their values.
+    fn activate(&mut self, step: i32) -> Option<MenuAction> {
+        let cycle = |i: usize, len: usize| ((i as i32 + step + len as i32) % len as i32) as usize;
+        match ITEMS[self.selected] {
+            Item::Resume => {
+                self.open = false;
+                None
+            },
+            Item::NewGame => {
+                self.open = false;
+                Some(MenuAction::NewGame)
+            },
+            Item::Mode => {
+                let i = MODES.iter().position(|&(_, mode)| mode == self.mode).unwrap();
+                self.mode = MODES[cycle(i, MODES.len())].1;
+                None
+            },
+            Item::Color => {
+                let i = COLORS.iter().position(|color| *color == self.color).unwrap();
+                self.color = COLORS[cycle(i, COLORS.len())].clone();
+                None
+            },
+            Item::Rules => {
+                let i = VARIANTS.iter().position(|&(_, rules)| rules == self.rules).unwrap();
+                self.rules = VARIANTS[cycle(i, VARIANTS.len())].1;
+                None
+            },
+            Item::Difficulty => {
+                let i = DIFFICULTIES.iter().position(|&(_, difficulty)| difficulty == self.difficulty).unwrap();
+                self.difficulty = DIFFICULTIES[cycle(i, DIFFICULTIES.len())].1;
+                Some(MenuAction::SetDifficulty(self.difficulty))
+            },
+            Item::Save => Some(MenuAction::Save),
+            Item::Load => {
+                self.open = false;
+                Some(MenuAction::Load)
+            },
+            Item::Quit => Some(MenuAction::Quit),
+        }
+    }
+}
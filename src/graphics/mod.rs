@@ -1,10 +1,14 @@
 extern crate glutin;
 extern crate glium_text;
 extern crate image;
-extern crate cgmath;
+extern crate time;
 
 mod app;
+mod layout;
+mod menu;
 mod settings;
 
-pub use self::app::App;
-pub use self::settings::Settings;
+pub use self::app::{App, SHORTCUTS};
+pub use self::layout::{Layout, PANEL_ROWS};
+pub use self::menu::{Menu, MenuAction, Mode, MODES};
+pub use self::settings::{Settings, Vec2f};
@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, Debug)]
 pub struct Vec2f {
     pub x: f64,
     pub y: f64,
@@ -17,4 +18,12 @@ impl Settings {
             board_margin: Vec2f { x: 40.0, y: 40.0 },
         }
     }
+
+    // Size of the board at the default window size, margins included.
+    pub fn board_size(&self) -> Vec2f {
+        Vec2f {
+            x: 18.0 * self.cell_size + 2.0 * self.board_margin.x,
+            y: 18.0 * self.cell_size + 2.0 * self.board_margin.y,
+        }
+    }
 }
@@ -2,7 +2,16 @@
 #![feature(range_contains)]
 #[macro_use]
 extern crate glium;
+#[macro_use]
+extern crate lazy_static;
 pub mod board;
+pub mod book;
+pub mod difficulty;
+pub mod eval;
 pub mod game;
+pub mod mcts;
 pub mod minimax;
+pub mod pns;
+pub mod solver;
+pub mod tuner;
 pub mod graphics;
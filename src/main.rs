@@ -5,11 +5,82 @@ extern crate glium;
 extern crate glutin;
 
 const DEFAULT_MODE: &'static str = "vs_ai";
+const DEFAULT_ENGINE: &'static str = "alphabeta";
+const DEFAULT_DIFFICULTY: &'static str = "hard";
+const DEFAULT_RULES: &'static str = "standard";
+const DEFAULT_PROOF_NODES: usize = 100_000;
+// Game saved and loaded from the menu
+const SAVE_FILE: &'static str = "gomoku-save.txt";
 
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
 use glium::DisplayBuild;
-use gomoku::board::{BoardState};
-use gomoku::game::{Game};
-use gomoku::graphics::{Settings, App};
+use gomoku::board::{Board, BoardState, Rules, Square, VARIANTS};
+use gomoku::book::{parse_game, Book, DEFAULT_BOOK};
+use gomoku::difficulty::{Difficulty, DIFFICULTIES};
+use gomoku::eval::{Evaluator, PatternEvaluator, PERSONALITIES};
+use gomoku::game::{Game, Engine};
+use gomoku::graphics::{Settings, App, Menu, MenuAction, Mode, MODES};
+use gomoku::pns::{self, Outcome};
+use gomoku::tuner::Sample;
+
+// Reads either 19 rows of 'B', 'W' or '_', or a position line as written by
+// gomoku-tune, which also gives the captures and the player to move.
+fn load_position(path: &Path) -> Result<(Board, Option<Square>), String> {
+    let mut data = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut data))
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let rows = data.lines().map(|line| line.trim()).filter(|line| !line.is_empty()).collect::<Vec<_>>();
+    if rows.len() == 1 {
+        let sample = Sample::parse(rows[0]).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok((sample.board, Some(sample.to_move)))
+    } else if rows.len() == 19 && rows.iter().all(|row| row.len() == 19) {
+        Ok((Board::from(rows.join("\n").as_str()), None))
+    } else {
+        Err(format!("{}: expected 19 rows of 19 squares", path.display()))
+    }
+}
+
+// Reads the rules and the moves of a game saved from the menu, the rules
+// being standard when not given.
+fn load_game(path: &Path) -> Result<(Rules, Vec<(usize, usize)>), String> {
+    let mut data = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut data))
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut rules = Rules::standard();
+    let mut moves = vec![];
+    for line in data.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        if line.starts_with("rules ") {
+            let name = line["rules ".len()..].trim();
+            rules = Rules::from_name(name).ok_or_else(|| format!("{}: unknown rules `{}`", path.display(), name))?;
+        }
+        else {
+            moves = parse_game(line).map(|(moves, _)| moves).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+    }
+    Ok((rules, moves))
+}
+
+// Saves the rules and the moves of the game, returning the message to show.
+fn save_game(game: &Game, path: &Path) -> String {
+    match File::create(path).and_then(|mut file| writeln!(file, "rules {}\n{}", game.rules().name(), game.to_text())) {
+        Ok(_) => format!("Game saved to {}", path.display()),
+        Err(e) => format!("Cannot save the game: {}", e),
+    }
+}
+
+// Plays for the human player, while the game is not over.
+fn play(game: &mut Game, pos: Option<(usize, usize)>) {
+    match game.board.game_state {
+        BoardState::InProgress | BoardState::FiveAligned(_, _) => game.play(pos),
+        _ => ()
+    }
+}
 
 fn main() {
 /*let board = gomoku::board::Board::from(
@@ -49,51 +120,286 @@ ___________________");
         .author("afaucher17 <afaucher17@users.noreply.github.com>")
         .about("Gomoku is a game derived from Go. The rules consists in aligning five stones from your color or capturing 10 stones from the opponent.")
         .arg(clap::Arg::with_name("mode")
-             .help("One or two player mode.")
+             .help("Two players, one player against the AI, or the AI against itself.")
              .takes_value(true)
              .short("m")
              .long("mode")
-             .possible_values(&["two_players", "vs_ai"]))
+             .possible_values(&MODES.iter().map(|&(name, _)| name).collect::<Vec<_>>()))
+        .arg(clap::Arg::with_name("rules")
+             .help("Rules of the game, standard ones by default: captures can be turned off, and so can the ban on double threes.")
+             .takes_value(true)
+             .short("r")
+             .long("rules")
+             .possible_values(&VARIANTS.iter().map(|&(name, _)| name).collect::<Vec<_>>()))
+        .arg(clap::Arg::with_name("engine")
+             .help("Engine used by the AI.")
+             .takes_value(true)
+             .short("e")
+             .long("engine")
+             .possible_values(&["alphabeta", "mcts"]))
+        .arg(clap::Arg::with_name("second_engine")
+             .help("Engine used by the second AI in ai_vs_ai mode, same as --engine by default.")
+             .takes_value(true)
+             .long("second-engine")
+             .possible_values(&["alphabeta", "mcts"]))
+        .arg(clap::Arg::with_name("difficulty")
+             .help("Strength of the AI, hard by default. Can be changed in game with keys 1 to 5.")
+             .takes_value(true)
+             .short("d")
+             .long("difficulty")
+             .possible_values(&DIFFICULTIES.iter().map(|&(name, _)| name).collect::<Vec<_>>()))
+        .arg(clap::Arg::with_name("personality")
+             .help("Evaluation weights shipped with the game.")
+             .takes_value(true)
+             .short("p")
+             .long("personality")
+             .possible_values(&PERSONALITIES.iter().map(|&(name, _)| name).collect::<Vec<_>>())
+             .conflicts_with("weights"))
+        .arg(clap::Arg::with_name("weights")
+             .help("File containing the evaluation weights of the AI.")
+             .takes_value(true)
+             .short("w")
+             .long("weights"))
+        .arg(clap::Arg::with_name("book")
+             .help("Opening book of the AI, built with gomoku-book. The one shipped with the game by default.")
+             .takes_value(true)
+             .short("b")
+             .long("book")
+             .conflicts_with("no_book"))
+        .arg(clap::Arg::with_name("no_book")
+             .help("Lets the AI search its first moves.")
+             .long("no-book"))
+        .arg(clap::Arg::with_name("ponder")
+             .help("Lets the alpha-beta AI search during your turn the position after the reply it expects.")
+             .long("ponder"))
+        .subcommand(clap::SubCommand::with_name("eval")
+             .about("Prints the detail of the evaluation of a position.")
+             .arg(clap::Arg::with_name("position")
+                  .help("File containing 19 rows of B, W or _, or a position line as read by gomoku-tune.")
+                  .required(true))
+             .arg(clap::Arg::with_name("color")
+                  .help("Player to move, for whom the position is evaluated. Black by default for boards without it.")
+                  .takes_value(true)
+                  .short("c")
+                  .long("color")
+                  .possible_values(&["black", "white"])))
+        .subcommand(clap::SubCommand::with_name("prove")
+             .about("Runs a proof-number search to find whether the player to move wins or loses a position.")
+             .arg(clap::Arg::with_name("position")
+                  .help("File containing 19 rows of B, W or _, or a position line as read by gomoku-tune.")
+                  .required(true))
+             .arg(clap::Arg::with_name("color")
+                  .help("Player to move. Black by default for boards without it.")
+                  .takes_value(true)
+                  .short("c")
+                  .long("color")
+                  .possible_values(&["black", "white"]))
+             .arg(clap::Arg::with_name("nodes")
+                  .help("Maximum number of nodes searched for each side.")
+                  .takes_value(true)
+                  .short("n")
+                  .long("nodes")))
         .get_matches();
 
-    let mode = match options.value_of("mode").unwrap_or(DEFAULT_MODE) {
-        "vs_ai" => true,
-        "two_players" => false,
-        _ => false,
+    let engine = |name: &str| match name {
+        "mcts" => Engine::Mcts,
+        _ => Engine::AlphaBeta,
+    };
+    let first_engine = options.value_of("engine").unwrap_or(DEFAULT_ENGINE);
+    let second_engine = options.value_of("second_engine").unwrap_or(first_engine);
+    let players = |mode: Mode| match mode {
+        Mode::VsAi => (Some(engine(first_engine)), None),
+        Mode::AiVsAi => (Some(engine(first_engine)), Some(engine(second_engine))),
+        Mode::TwoPlayers => (None, None),
     };
+    let mode = Mode::from_name(options.value_of("mode").unwrap_or(DEFAULT_MODE)).unwrap();
+    let rules = Rules::from_name(options.value_of("rules").unwrap_or(DEFAULT_RULES)).unwrap();
+    let difficulty = Difficulty::from_name(options.value_of("difficulty").unwrap_or(DEFAULT_DIFFICULTY)).unwrap();
+    let evaluator = match options.value_of("weights") {
+        Some(path) => PatternEvaluator::load(Path::new(path)).unwrap_or_else(|e| {
+            println!("Cannot load the evaluation weights: {}", e);
+            process::exit(1);
+        }),
+        None => PatternEvaluator::personality(options.value_of("personality").unwrap_or("default")).unwrap(),
+    };
+
+    if let Some(options) = options.subcommand_matches("eval") {
+        let (board, to_move) = load_position(Path::new(options.value_of("position").unwrap())).unwrap_or_else(|e| {
+            println!("Cannot load the position: {}", e);
+            process::exit(1);
+        });
+        let player = match options.value_of("color") {
+            Some("white") => Square::White,
+            Some(_) => Square::Black,
+            None => to_move.unwrap_or(Square::Black),
+        };
+        print!("{}", board.explain_evaluation(&player, &player, &evaluator.weights));
+        return;
+    }
+
+    if let Some(options) = options.subcommand_matches("prove") {
+        let (board, to_move) = load_position(Path::new(options.value_of("position").unwrap())).unwrap_or_else(|e| {
+            println!("Cannot load the position: {}", e);
+            process::exit(1);
+        });
+        let player = match options.value_of("color") {
+            Some("white") => Square::White,
+            Some(_) => Square::Black,
+            None => to_move.unwrap_or(Square::Black),
+        };
+        let max_nodes = match options.value_of("nodes") {
+            Some(value) => value.parse::<usize>().unwrap_or_else(|_| {
+                println!("Invalid value `{}` for --nodes", value);
+                process::exit(1);
+            }),
+            None => DEFAULT_PROOF_NODES,
+        };
+        let proof = pns::prove(&board, &player, max_nodes);
+        let line: Vec<String> = proof.main_line.iter().map(|&pos| Board::notation(pos)).collect();
+        match proof.result {
+            Outcome::Win => println!("{:?} wins: {}", player, line.join(" ")),
+            Outcome::Loss => println!("{:?} loses: {}", player, line.join(" ")),
+            Outcome::Unknown => println!("Unknown, most-proving line: {}", line.join(" ")),
+        }
+        println!("{} nodes searched", proof.nodes);
+        return;
+    }
+
+    let book = if options.is_present("no_book") {
+        Book::new()
+    } else {
+        match options.value_of("book") {
+            Some(path) => Book::load(Path::new(path)).unwrap_or_else(|e| {
+                println!("Cannot load the opening book: {}", e);
+                process::exit(1);
+            }),
+            None => Book::from_text(DEFAULT_BOOK).unwrap(),
+        }
+    };
+
     let settings = Settings::new();
 
     let display = glium::glutin::WindowBuilder::new()
         .with_title("Gomoku".to_string())
         .with_dimensions(settings.win_size.x as u32, settings.win_size.y as u32)
+        .with_min_dimensions(settings.win_size.x as u32 / 2, settings.win_size.y as u32 / 2)
         .with_vsync()
         .build_glium()
         .unwrap();
-//    window.set_window_resize_callback(Some(redraw as fn(u32, u32)));
 
-    let app = App::new(&display);
+    let mut window_size = display.get_window().unwrap().get_inner_size_pixels().unwrap();
+    let mut app = App::new(&display, settings);
     let mut mouse_pos = [0f64, 0f64];
-    let mut game = Game::new(mode);
+    let evaluator: Arc<Evaluator + Send + Sync> = Arc::new(evaluator);
+    let book = Arc::new(book);
+    let ponder = options.is_present("ponder");
+    let new_game = |menu: &Menu, rules: Rules| {
+        let (first, second) = players(menu.mode);
+        // Against the AI, the first player is the AI
+        let first_color = match menu.mode {
+            Mode::VsAi => menu.color.as_ref().map(|color| color.opposite()),
+            _ => menu.color.clone(),
+        };
+        let mut game = Game::new(first, second, first_color, rules, evaluator.clone(), menu.difficulty, book.clone());
+        game.set_pondering(ponder);
+        game
+    };
+    let mut menu = Menu::new(mode, rules, difficulty);
+    let mut game = new_game(&menu, menu.rules);
 
     'main: loop {
-        app.on_render(&display, &game);
+        app.on_render(&display, &game, &menu);
         for event in display.poll_events() {
+            let mut action = None;
             match event {
-                glutin::Event::KeyboardInput(_, _, Some(glutin::VirtualKeyCode::Escape)) |
                 glutin::Event::Closed => break 'main,
+                glutin::Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(glutin::VirtualKeyCode::Escape)) if app.show_help =>
+                    app.show_help = false,
+                glutin::Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(glutin::VirtualKeyCode::Escape)) =>
+                    menu.open = !menu.open,
+                glutin::Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(key)) if menu.open =>
+                    action = menu.on_key(key),
+                glutin::Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(glutin::VirtualKeyCode::D)) =>
+                    app.show_evaluation = !app.show_evaluation,
+                glutin::Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(glutin::VirtualKeyCode::N)) =>
+                    app.show_move_numbers = !app.show_move_numbers,
+                glutin::Event::KeyboardInput(glutin::ElementState::Pressed, _, Some(key)) => match key {
+                    glutin::VirtualKeyCode::Left => app.move_cursor(-1, 0),
+                    glutin::VirtualKeyCode::Right => app.move_cursor(1, 0),
+                    glutin::VirtualKeyCode::Up => app.move_cursor(0, -1),
+                    glutin::VirtualKeyCode::Down => app.move_cursor(0, 1),
+                    glutin::VirtualKeyCode::Return | glutin::VirtualKeyCode::Space if app.cursor.is_some() =>
+                        play(&mut game, app.cursor),
+                    glutin::VirtualKeyCode::U => game.undo(),
+                    glutin::VirtualKeyCode::Y => game.redo(),
+                    glutin::VirtualKeyCode::R => action = Some(MenuAction::NewGame),
+                    glutin::VirtualKeyCode::S => action = Some(MenuAction::Save),
+                    glutin::VirtualKeyCode::H => game.request_hint(),
+                    glutin::VirtualKeyCode::P => {
+                        let paused = game.is_paused();
+                        game.set_paused(!paused);
+                    },
+                    glutin::VirtualKeyCode::C => app.show_coordinates = !app.show_coordinates,
+                    glutin::VirtualKeyCode::V => app.show_variation = !app.show_variation,
+                    glutin::VirtualKeyCode::M => {
+                        app.show_heat_map = !app.show_heat_map;
+                        game.set_heat_map(app.show_heat_map);
+                    },
+                    glutin::VirtualKeyCode::F1 => app.show_help = !app.show_help,
+                    glutin::VirtualKeyCode::Key1 => action = Some(MenuAction::SetDifficulty(Difficulty::Beginner)),
+                    glutin::VirtualKeyCode::Key2 => action = Some(MenuAction::SetDifficulty(Difficulty::Easy)),
+                    glutin::VirtualKeyCode::Key3 => action = Some(MenuAction::SetDifficulty(Difficulty::Medium)),
+                    glutin::VirtualKeyCode::Key4 => action = Some(MenuAction::SetDifficulty(Difficulty::Hard)),
+                    glutin::VirtualKeyCode::Key5 => action = Some(MenuAction::SetDifficulty(Difficulty::Master)),
+                    _ => (),
+                },
+                glutin::Event::Resized(w, h) => window_size = (w, h),
+                glutin::Event::MouseInput(glutin::ElementState::Released, glutin::MouseButton::Left) if menu.open =>
+                    action = menu.on_click(&app.layout(window_size), &mouse_pos),
                 glutin::Event::MouseInput(glutin::ElementState::Released, glutin::MouseButton::Left) => {
-                    let pos;
-                    {
-                        pos = app.on_click(&mouse_pos, display.get_window().unwrap().get_inner_size_pixels().unwrap())
-                    }
-                    match game.board.game_state {
-                        BoardState::InProgress | BoardState::FiveAligned(_, _) => game.play(pos),
-                        _ => ()
-                    }
+                    let pos = app.on_click(&mouse_pos, window_size);
+                    play(&mut game, pos);
                 }
-                glutin::Event::MouseMoved(x, y) => mouse_pos = [x as f64, y as f64],
+                glutin::Event::MouseMoved(x, y) => {
+                    mouse_pos = [x as f64, y as f64];
+                    app.on_mouse_moved(&mouse_pos, window_size);
+                    if menu.open {
+                        menu.on_mouse_moved(&app.layout(window_size), &mouse_pos);
+                    }
+                },
                 _ => {}
             }
+            match action {
+                Some(MenuAction::NewGame) => {
+                    game = new_game(&menu, menu.rules);
+                    game.set_heat_map(app.show_heat_map);
+                },
+                Some(MenuAction::SetDifficulty(difficulty)) => {
+                    menu.difficulty = difficulty;
+                    game.set_difficulty(difficulty);
+                },
+                Some(MenuAction::Save) => app.message = save_game(&game, Path::new(SAVE_FILE)),
+                Some(MenuAction::Load) => {
+                    let loaded = load_game(Path::new(SAVE_FILE)).and_then(|(rules, moves)| {
+                        let mut loaded = new_game(&menu, rules);
+                        loaded.replay(&moves).map(|_| loaded)
+                    });
+                    match loaded {
+                        Ok(loaded) => {
+                            game = loaded;
+                            menu.rules = game.rules();
+                            game.set_heat_map(app.show_heat_map);
+                            app.message = format!("Game loaded from {}", SAVE_FILE);
+                        },
+                        Err(e) => {
+                            app.message = format!("Cannot load the game: {}", e);
+                            menu.open = true;
+                        },
+                    }
+                },
+                Some(MenuAction::Quit) => break 'main,
+                None => (),
+            }
         }
         game.update();
     }
@@ -0,0 +1,138 @@
+extern crate rand;
+extern crate time;
+
+use board::{Board, BoardState, Move, Square};
+use eval::Evaluator;
+
+use std::f64;
+use std::sync::atomic::{AtomicBool, Ordering};
+use self::rand::Rng;
+use self::time::PreciseTime;
+
+// Exploration constant of the UCT formula.
+const EXPLORATION: f64 = 1.4;
+// Number of random moves played before falling back on the static evaluation.
+const PLAYOUT_DEPTH: usize = 10;
+
+struct Node
+{
+    pos: Option<(usize, usize)>,
+    // Color of the player who moved into this node
+    color: Square,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<(usize, usize)>,
+    visits: u32,
+    wins: f64,
+}
+
+impl Node
+{
+    fn uct(&self, parent_visits: u32) -> f64 {
+        self.wins / self.visits as f64
+            + EXPLORATION * ((parent_visits as f64).ln() / self.visits as f64).sqrt()
+    }
+}
+
+fn play(board: &Board, pos: (usize, usize), color: &Square, start: PreciseTime) -> Option<Board> {
+    match board.play_at(Some(pos), color, start, true) {
+        Move::Legal(child, _, _, _) => Some(child),
+        _ => None,
+    }
+}
+
+// Plays random moves among the ones proposed by `Board::get_plays`, and
+// returns the result for `player`: 1 for a win, 0 for a loss.
+fn playout(board: &Board, player: &Square, to_move: &Square, start: PreciseTime, evaluator: &Evaluator) -> f64 {
+    let mut rng = rand::thread_rng();
+    let mut board = board.clone();
+    let mut color = to_move.clone();
+    for _ in 0..PLAYOUT_DEPTH {
+        match board.game_state {
+            BoardState::Victory(ref winner) => return if *winner == *player { 1.0 } else { 0.0 },
+            BoardState::Draw => return 0.5,
+            _ => (),
+        }
+        let mut plays = board.get_plays(&color);
+        let mut next = None;
+        while next.is_none() && !plays.is_empty() {
+            let i = rng.gen_range(0, plays.len());
+            next = play(&board, plays.swap_remove(i), &color, start);
+        }
+        match next {
+            Some(child) => board = child,
+            None => break,
+        }
+        color = color.opposite();
+    }
+    match board.game_state {
+        BoardState::Victory(ref winner) => if *winner == *player { 1.0 } else { 0.0 },
+        _ => {
+            let value = evaluator.evaluate(&board, player, &color);
+            if value > 0 { 1.0 } else if value < 0 { 0.0 } else { 0.5 }
+        }
+    }
+}
+
+fn new_node(board: &Board, pos: Option<(usize, usize)>, color: &Square, parent: Option<usize>) -> Node {
+    let mut rng = rand::thread_rng();
+    let mut untried = if board.is_terminal() { vec![] } else { board.get_plays(&color.opposite()) };
+    rng.shuffle(&mut untried);
+    Node {
+        pos: pos,
+        color: color.clone(),
+        parent: parent,
+        children: vec![],
+        untried: untried,
+        visits: 0,
+        wins: 0.0,
+    }
+}
+
+// Monte Carlo tree search with UCT selection, running for `time_limit`
+// milliseconds or until `cancel` is set. Returns the most visited move for
+// `player`.
+pub fn mcts(board: &Board, player: &Square, start: PreciseTime, time_limit: i64, cancel: &AtomicBool, evaluator: &Evaluator) -> Option<(usize, usize)> {
+    let mut nodes = vec![new_node(board, None, &player.opposite(), None)];
+    while start.to(PreciseTime::now()).num_milliseconds() < time_limit && !cancel.load(Ordering::Relaxed) {
+        // Selection
+        let mut index = 0;
+        let mut current = board.clone();
+        while nodes[index].untried.is_empty() && !nodes[index].children.is_empty() {
+            let parent_visits = nodes[index].visits;
+            let next = *nodes[index].children.iter()
+                .max_by(|&&a, &&b| nodes[a].uct(parent_visits)
+                        .partial_cmp(&nodes[b].uct(parent_visits)).unwrap())
+                .unwrap();
+            current = play(&current, nodes[next].pos.unwrap(), &nodes[next].color, start).unwrap();
+            index = next;
+        }
+
+        // Expansion
+        let color = nodes[index].color.opposite();
+        while let Some(pos) = nodes[index].untried.pop() {
+            if let Some(child) = play(&current, pos, &color, start) {
+                let child_index = nodes.len();
+                nodes.push(new_node(&child, Some(pos), &color, Some(index)));
+                nodes[index].children.push(child_index);
+                current = child;
+                index = child_index;
+                break;
+            }
+        }
+
+        // Simulation
+        let result = playout(&current, player, &nodes[index].color.opposite(), start, evaluator);
+
+        // Backpropagation
+        let mut next = Some(index);
+        while let Some(i) = next {
+            nodes[i].visits += 1;
+            nodes[i].wins += if nodes[i].color == *player { result } else { 1.0 - result };
+            next = nodes[i].parent;
+        }
+    }
+    nodes[0].children.iter()
+        .max_by_key(|&&c| nodes[c].visits)
+        .and_then(|&c| nodes[c].pos)
+}
@@ -1,17 +1,30 @@
 extern crate time;
 
-use board::{Board, Square, Move};
+use board::{Board, BoardState, Square, Move};
+use eval::Evaluator;
 
 use std::cmp;
 use std::cmp::Ordering;
+use std::fmt;
 use std::i32;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use self::time::PreciseTime;
 
+// Default time given to the AI for each move, in milliseconds.
+pub const TIME_LIMIT: i64 = 500;
+// Default limits of the quiescence search: forcing moves played past the
+// search depth, and forcing moves tried in each position.
+pub const QUIESCENCE_DEPTH: usize = 6;
+pub const QUIESCENCE_WIDTH: usize = 8;
+// Score of a position lost by the player searching, beyond any evaluation
+const LOSS: i32 = -1_000_000_000;
+
 #[derive(PartialEq, Eq, PartialOrd, Debug)]
 pub struct Decision
 {
-    score: i32,
+    pub score: i32,
     pub pos: Option<(usize, usize)>
 }
 
@@ -21,6 +34,15 @@ pub struct TTEntry
     score: i32,
     tttype: TTType,
     depth: usize,
+    // Move found best or causing a cutoff, tried first next time
+    best: Option<(usize, usize)>,
+}
+
+impl TTEntry
+{
+    pub fn best_move(&self) -> Option<(usize, usize)> {
+        self.best
+    }
 }
 
 #[derive(Clone)]
@@ -41,40 +63,241 @@ impl Ord for Decision
     }
 }
 
-static mut KILLER_MOVES: [[Option<(usize, usize)>; 2]; 12] = [
-    [None, None], [None, None], [None, None], [None, None],
-    [None, None], [None, None], [None, None], [None, None],
-    [None, None], [None, None], [None, None], [None, None],
-];
+// Counters of one search, showing how well moves are ordered: the better
+// the ordering, the more cutoffs happen on the first move tried.
+#[derive(Clone, Debug)]
+pub struct SearchStats
+{
+    pub nodes: u64,
+    pub tt_hits: u64,
+    pub cutoffs: u64,
+    pub first_move_cutoffs: u64,
+    // Nodes of the quiescence search, also counted in `nodes`
+    pub quiescence_nodes: u64,
+}
+
+impl SearchStats
+{
+    pub fn new() -> SearchStats {
+        SearchStats { nodes: 0, tt_hits: 0, cutoffs: 0, first_move_cutoffs: 0, quiescence_nodes: 0 }
+    }
+
+    pub fn first_move_rate(&self) -> f64 {
+        if self.cutoffs == 0 { 0.0 } else { self.first_move_cutoffs as f64 / self.cutoffs as f64 }
+    }
+}
+
+impl fmt::Display for SearchStats
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} nodes ({} quiescence), {} transposition hits, {} cutoffs ({:.1}% on the first move)",
+               self.nodes, self.quiescence_nodes, self.tt_hits, self.cutoffs, 100.0 * self.first_move_rate())
+    }
+}
+
+// History heuristic: moves causing cutoffs, by color and square, scored by
+// the square of the remaining depth.
+pub struct History
+{
+    table: [[u32; 361]; 2],
+}
 
-fn get_plays(board: &Board, color: &Square, depth: usize) -> Vec<(usize, usize)>
+impl History
+{
+    pub fn new() -> History {
+        History { table: [[0; 361]; 2] }
+    }
+
+    fn index(color: &Square) -> usize {
+        match *color {
+            Square::White => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn score(&self, color: &Square, pos: (usize, usize)) -> u32 {
+        self.table[History::index(color)][pos.0 * 19 + pos.1]
+    }
+
+    pub fn add(&mut self, color: &Square, pos: (usize, usize), depth: usize) {
+        let score = &mut self.table[History::index(color)][pos.0 * 19 + pos.1];
+        *score = score.saturating_add((depth * depth) as u32);
+    }
+}
+
+// State of one search, owned by the engine running it so that independent
+// searches can run at the same time.
+pub struct SearchContext
+{
+    pub start: PreciseTime,
+    // In milliseconds
+    pub time_limit: i64,
+    pub ttmap: HashMap<u64, TTEntry>,
+    pub history: History,
+    pub stats: SearchStats,
+    // Set from another thread to stop the search as if the time had run out
+    pub cancel: Arc<AtomicBool>,
+    // Limits of the quiescence search, which is skipped when the depth is 0
+    pub quiescence_depth: usize,
+    pub quiescence_width: usize,
+    // Two killer moves for each remaining depth
+    killers: Vec<[Option<(usize, usize)>; 2]>,
+}
+
+impl SearchContext
+{
+    pub fn new(start: PreciseTime, time_limit: i64, ttmap: HashMap<u64, TTEntry>) -> SearchContext {
+        SearchContext {
+            start: start,
+            time_limit: time_limit,
+            ttmap: ttmap,
+            history: History::new(),
+            stats: SearchStats::new(),
+            cancel: Arc::new(AtomicBool::new(false)),
+            quiescence_depth: QUIESCENCE_DEPTH,
+            quiescence_width: QUIESCENCE_WIDTH,
+            killers: vec![],
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.cancel.load(AtomicOrdering::Relaxed)
+            || self.start.to(PreciseTime::now()).num_milliseconds() >= self.time_limit
+    }
+
+    fn killer_moves(&self, depth: usize) -> [Option<(usize, usize)>; 2] {
+        self.killers.get(depth).cloned().unwrap_or([None, None])
+    }
+
+    fn add_killer_move(&mut self, pos: Option<(usize, usize)>, depth: usize) {
+        if self.killers.len() <= depth {
+            self.killers.resize(depth + 1, [None, None]);
+        }
+        let killers = &mut self.killers[depth];
+        match (killers[0], killers[1]) {
+            (None, None) => killers[0] = pos,
+            (Some(_), None) => killers[1] = pos,
+            (old, Some(_)) => {
+                killers[1] = old;
+                killers[0] = pos;
+            }
+        }
+    }
+}
+
+// The move of the transposition table first, then the killer moves, then
+// the others by static score and history.
+fn get_plays(board: &Board, color: &Square, depth: usize, tt_move: Option<(usize, usize)>, ctx: &SearchContext) -> Vec<(usize, usize)>
 {
     let mut v: Vec<(usize, usize)> = Vec::new();
-    unsafe {
-        match (KILLER_MOVES[depth][0], KILLER_MOVES[depth][1])
-        {
-            (None, None) => (),
-            (Some(pos), None) | (None, Some(pos))=> v.push(pos),
-            (Some(pos), Some(pos2)) => v.extend(vec![pos, pos2]),
+    v.extend(tt_move);
+    for killer in ctx.killer_moves(depth).iter() {
+        match *killer {
+            Some(pos) if !v.contains(&pos) => v.push(pos),
+            _ => (),
         }
     }
-    v.extend(board.get_plays(color));
+    let mut plays = board.get_plays(color).into_iter()
+        .filter(|pos| !v.contains(pos))
+        .map(|pos| (board.move_score(pos, color), ctx.history.score(color, pos), pos))
+        .collect::<Vec<_>>();
+    plays.sort_by(|a, b| (b.0, b.1).cmp(&(a.0, a.1)));
+    v.extend(plays.into_iter().map(|(_, _, pos)| pos));
     v
 }
 
-fn add_killer_move(pos: Option<(usize, usize)>, depth: usize)
+// Moves of `color` that keep the position from being quiet, best first, and
+// whether `color` must play one of them: fives, fours, and captures winning
+// the game, or when the opponent threatens a five, the blocks and the
+// captures breaking the threat.
+fn forcing_moves(board: &Board, color: &Square) -> (Vec<(usize, usize)>, bool)
 {
-    unsafe {
-        match (KILLER_MOVES[depth][0], KILLER_MOVES[depth][1])
-        {
-            (None, None) => KILLER_MOVES[depth][0] = pos,
-            (Some(_), None) => KILLER_MOVES[depth][1] = pos,
-            (old, Some(_)) => {
-                KILLER_MOVES[depth][1] = old;
-                KILLER_MOVES[depth][0] = pos;
+    let opponent = color.opposite();
+    let fives = board.check_five_squares(color);
+    let captures = board.check_capture_squares(color);
+    let (mut moves, forced) = match board.game_state {
+        // Only a capture can break a five
+        BoardState::FiveAligned(ref aligned, _) if *aligned == opponent => (captures, true),
+        _ => {
+            let threats = board.check_five_squares(&opponent);
+            if threats.is_empty() {
+                let mut moves = fives;
+                moves.extend(board.check_four_squares(color));
+                if board.get_score(color) >= 8 {
+                    moves.extend(captures);
+                }
+                (moves, false)
+            }
+            else {
+                let mut moves = fives;
+                moves.extend(threats.iter().cloned());
+                moves.extend(captures.into_iter().filter(|&pos| match board.play_at(Some(pos), color, PreciseTime::now(), false) {
+                    Move::Legal(child, _, _, _) => child.check_five_squares(&opponent).len() < threats.len(),
+                    _ => false,
+                }));
+                (moves, true)
+            }
+        }
+    };
+    moves.sort_by_key(|&pos| (-board.move_score(pos, color), pos));
+    moves.dedup();
+    (moves, forced)
+}
+
+// Searches the forcing moves past the horizon of `minimax`, until the
+// position is quiet or `depth` more moves are played. The player to move
+// can stop with the static evaluation, unless the opponent threatens a five,
+// and loses without a legal reply to it. `None` when the search is stopped.
+fn quiesce(board: &Board,
+           depth: usize,
+           mut alpha: i32,
+           mut beta: i32,
+           maximizing_player: bool,
+           player: &Square,
+           ctx: &mut SearchContext,
+           evaluator: &Evaluator
+          )
+    -> Option<i32>
+{
+    if ctx.is_stopped() {
+        return None;
+    }
+    let current_color = match maximizing_player { true => player.clone(), false => player.opposite() };
+    let stand_pat = evaluator.evaluate(board, &player, &current_color);
+    if depth == 0 || board.is_terminal() {
+        return Some(stand_pat);
+    }
+    let (moves, forced) = forcing_moves(board, &current_color);
+    let lost = if maximizing_player { LOSS } else { -LOSS };
+    if moves.is_empty() {
+        return Some(if forced { lost } else { stand_pat });
+    }
+    ctx.stats.nodes += 1;
+    ctx.stats.quiescence_nodes += 1;
+
+    let mut best = if forced { None } else { Some(stand_pat) };
+    let mut searched = 0;
+    for pos in moves {
+        if searched == ctx.quiescence_width || best.map_or(false, |score| if maximizing_player { score >= beta } else { score <= alpha }) {
+            break;
+        }
+        if let Move::Legal(child, _, _, _) = board.play_at(Some(pos), &current_color, ctx.start, true) {
+            searched += 1;
+            let score = match quiesce(&child, depth - 1, alpha, beta, !maximizing_player, player, ctx, evaluator) {
+                Some(score) => score,
+                None => return None,
+            };
+            if maximizing_player {
+                best = Some(best.map_or(score, |best| cmp::max(best, score)));
+                alpha = cmp::max(alpha, score);
+            }
+            else {
+                best = Some(best.map_or(score, |best| cmp::min(best, score)));
+                beta = cmp::min(beta, score);
             }
         }
     }
+    Some(best.unwrap_or(lost))
 }
 
 pub fn minimax(board: &Board,
@@ -84,25 +307,27 @@ pub fn minimax(board: &Board,
                maximizing_player: bool,
                prev_play: Option<(usize, usize)>,
                player: &Square,
-               start: PreciseTime,
-               ttmap: &mut HashMap<u64, TTEntry>
+               ctx: &mut SearchContext,
+               evaluator: &Evaluator
               )
     -> Decision
 {
     let current_color = match maximizing_player { true => player.clone(), false => player.opposite() };
-    // Time-out
-    if start.to(PreciseTime::now()).num_milliseconds() >= 500 {
+    // Time-out or cancellation
+    if ctx.is_stopped() {
         return Decision {
             score: 0,
             pos: None
         };
     }
+    ctx.stats.nodes += 1;
 
     // Transition Table
     {
-        let tte = ttmap.get(&board.hash);
+        let tte = ctx.ttmap.get(&board.hash);
         if tte.is_some() && tte.unwrap().depth >= depth
         {
+            ctx.stats.tt_hits += 1;
             let tte = tte.unwrap();
             match tte.tttype {
                 TTType::ExactValue if prev_play.is_some() => return Decision { score: tte.score, pos: prev_play },
@@ -118,15 +343,19 @@ pub fn minimax(board: &Board,
 
     // Terminal Node
     if depth == 0 || board.is_terminal() {
-        let value = board.evaluation(&player, &current_color);
+        let quiescence_depth = ctx.quiescence_depth;
+        let value = match quiesce(board, quiescence_depth, alpha, beta, maximizing_player, player, ctx, evaluator) {
+            Some(value) => value,
+            None => return Decision { score: 0, pos: None },
+        };
         if value <= alpha {
-            ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Lowerbound, depth: depth });
+            ctx.ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Lowerbound, depth: depth, best: None });
         }
         else if value >= beta {
-            ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Upperbound, depth: depth });
+            ctx.ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Upperbound, depth: depth, best: None });
         }
         else {
-            ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::ExactValue, depth: depth });
+            ctx.ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::ExactValue, depth: depth, best: None });
         }
         return Decision {
             score: value,
@@ -134,34 +363,42 @@ pub fn minimax(board: &Board,
         };
     }
 
-    let plays: Vec<(usize, usize)> = get_plays(board, &current_color, depth - 1);
+    let tt_move = ctx.ttmap.get(&board.hash).and_then(|tte| tte.best);
+    let plays: Vec<(usize, usize)> = get_plays(board, &current_color, depth - 1, tt_move, ctx);
+    let mut tried = 0;
     if maximizing_player {
         let mut v = Decision { score: i32::MIN, pos: None };
         //println!(" (DEPTH = {}, POS = {:?}, (MAXIMAZING):", depth, prev_play);
         for pos in plays {
-            if let Move::Legal(child, _, _, _) = board.play_at(Some(pos), &current_color, start, true)
+            if let Move::Legal(child, _, _, _) = board.play_at(Some(pos), &current_color, ctx.start, true)
             {
+                tried += 1;
                 {
-                    let decision = minimax(&child, depth - 1, alpha, beta, false, Some(pos), player, start, ttmap);
+                    let decision = minimax(&child, depth - 1, alpha, beta, false, Some(pos), player, ctx, evaluator);
                     if decision.pos == None { return decision; }
                     v = cmp::max(v, decision);
                     alpha = cmp::max(alpha, v.score);
                 }
                 if alpha >= beta {
-                    add_killer_move(v.pos, depth - 1);
+                    ctx.add_killer_move(v.pos, depth - 1);
+                    if let Some(cut) = v.pos {
+                        ctx.history.add(&current_color, cut, depth);
+                    }
+                    ctx.stats.cutoffs += 1;
+                    if tried == 1 { ctx.stats.first_move_cutoffs += 1; }
                     break ; // beta cut-off
                 }
             }
         }
         let value = v.score;
         if value <= alpha {
-            ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Lowerbound, depth: depth });
+            ctx.ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Lowerbound, depth: depth, best: v.pos });
         }
         else if value >= beta {
-            ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Upperbound, depth: depth });
+            ctx.ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Upperbound, depth: depth, best: v.pos });
         }
         else {
-            ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::ExactValue, depth: depth });
+            ctx.ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::ExactValue, depth: depth, best: v.pos });
         }
         let decision = Decision { 
             score: value,
@@ -172,28 +409,34 @@ pub fn minimax(board: &Board,
     else {
         let mut v = Decision { score: i32::MAX, pos: None };
         for pos in plays {
-            if let Move::Legal(child, _, _, _) = board.play_at(Some(pos), &current_color, start, true) {
+            if let Move::Legal(child, _, _, _) = board.play_at(Some(pos), &current_color, ctx.start, true) {
+                tried += 1;
                 {
-                    let decision = minimax(&child, depth - 1, alpha, beta, true, Some(pos), player, start, ttmap);
+                    let decision = minimax(&child, depth - 1, alpha, beta, true, Some(pos), player, ctx, evaluator);
                     if decision.pos == None { return decision; }
                     v = cmp::min(v, decision);
                     beta = cmp::min(beta, v.score);
                 }
                 if beta <= alpha {
-                    add_killer_move(v.pos, depth);
+                    ctx.add_killer_move(v.pos, depth);
+                    if let Some(cut) = v.pos {
+                        ctx.history.add(&current_color, cut, depth);
+                    }
+                    ctx.stats.cutoffs += 1;
+                    if tried == 1 { ctx.stats.first_move_cutoffs += 1; }
                     break ; // alpha cut-off
                 }
             }
         }
         let value = v.score;
         if value <= alpha {
-            ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Lowerbound, depth: depth });
+            ctx.ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Lowerbound, depth: depth, best: v.pos });
         }
         else if value >= beta {
-            ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Upperbound, depth: depth });
+            ctx.ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::Upperbound, depth: depth, best: v.pos });
         }
         else {
-            ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::ExactValue, depth: depth });
+            ctx.ttmap.insert(board.hash, TTEntry { score: value, tttype: TTType::ExactValue, depth: depth, best: v.pos });
         }
         let decision = Decision {
             score: v.score,
@@ -202,3 +445,57 @@ pub fn minimax(board: &Board,
         return decision;
     }
 }
+
+// Line expected after the move `first` of `color`, following the best moves
+// stored in the transposition table, at most `max_len` moves long.
+pub fn principal_variation(board: &Board,
+                           color: &Square,
+                           first: (usize, usize),
+                           ttmap: &HashMap<u64, TTEntry>,
+                           max_len: usize
+                          )
+    -> Vec<(usize, usize)>
+{
+    let mut variation = vec![];
+    let mut board = board.clone();
+    let mut color = color.clone();
+    let mut next = Some(first);
+    while let Some(pos) = next {
+        if variation.len() >= max_len {
+            break;
+        }
+        match board.play_at(Some(pos), &color, PreciseTime::now(), true) {
+            Move::Legal(child, _, _, _) => board = child,
+            _ => break,
+        }
+        variation.push(pos);
+        if board.is_terminal() {
+            break;
+        }
+        color = color.opposite();
+        next = ttmap.get(&board.hash).and_then(|entry| entry.best);
+    }
+    variation
+}
+
+// Scores of every legal move of `player` searched to `depth`, each with a full
+// window so that the moves other than the best one get an exact score as
+// well. `None` when the time runs out.
+pub fn root_moves(board: &Board,
+                  depth: usize,
+                  player: &Square,
+                  ctx: &mut SearchContext,
+                  evaluator: &Evaluator
+                 )
+    -> Option<Vec<((usize, usize), i32)>>
+{
+    let mut moves = Vec::new();
+    for pos in board.get_plays(player) {
+        if let Move::Legal(child, _, _, _) = board.play_at(Some(pos), player, ctx.start, true) {
+            let decision = minimax(&child, depth - 1, i32::MIN, i32::MAX, false, Some(pos), player, ctx, evaluator);
+            if decision.pos == None { return None; }
+            moves.push((pos, decision.score));
+        }
+    }
+    Some(moves)
+}
@@ -0,0 +1,217 @@
+extern crate time;
+
+use board::{Board, BoardState, Move, Square};
+
+use std::u32;
+use self::time::PreciseTime;
+
+const INFINITY: u32 = u32::MAX;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome
+{
+    Win,
+    Loss,
+    Unknown,
+}
+
+#[derive(Clone, Debug)]
+pub struct Proof
+{
+    pub result: Outcome,
+    pub main_line: Vec<(usize, usize)>,
+    pub nodes: usize,
+}
+
+struct Node
+{
+    pos: Option<(usize, usize)>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    proof: u32,
+    disproof: u32,
+    expanded: bool,
+}
+
+// Proof-number search proving that `prover` wins the position. OR nodes are
+// the ones where `prover` is to move and only try the moves proposed by
+// `Board::get_plays`, while AND nodes try every legal move so that a proven
+// win holds against any defense.
+pub struct ProofSearch
+{
+    root: Board,
+    to_move: Square,
+    prover: Square,
+    nodes: Vec<Node>,
+    start: PreciseTime,
+}
+
+fn add(a: u32, b: u32) -> u32 {
+    a.saturating_add(b)
+}
+
+impl ProofSearch
+{
+    pub fn new(board: &Board, to_move: &Square, prover: &Square) -> ProofSearch {
+        let mut search = ProofSearch {
+            root: board.clone(),
+            to_move: to_move.clone(),
+            prover: prover.clone(),
+            nodes: vec![Node { pos: None, parent: None, children: vec![], proof: 1, disproof: 1, expanded: false }],
+            start: PreciseTime::now(),
+        };
+        let (proof, disproof) = search.evaluate(board);
+        search.nodes[0].proof = proof;
+        search.nodes[0].disproof = disproof;
+        search
+    }
+
+    fn evaluate(&self, board: &Board) -> (u32, u32) {
+        match board.game_state {
+            BoardState::Victory(ref color) if *color == self.prover => (0, INFINITY),
+            BoardState::Victory(_) | BoardState::Draw => (INFINITY, 0),
+            _ => (1, 1),
+        }
+    }
+
+    fn is_or(&self, depth: usize) -> bool {
+        (depth % 2 == 0) == (self.to_move == self.prover)
+    }
+
+    fn color_at(&self, depth: usize) -> Square {
+        if depth % 2 == 0 { self.to_move.clone() } else { self.to_move.opposite() }
+    }
+
+    // Walks down to the most-proving node, replaying its moves on the root board.
+    fn select(&self) -> (usize, usize, Board) {
+        let mut index = 0;
+        let mut depth = 0;
+        let mut board = self.root.clone();
+        while self.nodes[index].expanded {
+            let node = &self.nodes[index];
+            let next = if self.is_or(depth) {
+                node.children.iter().min_by_key(|&&c| self.nodes[c].proof)
+            } else {
+                node.children.iter().min_by_key(|&&c| self.nodes[c].disproof)
+            };
+            match next {
+                Some(&child) => {
+                    if let Move::Legal(next_board, _, _, _) = board.play_at(self.nodes[child].pos, &self.color_at(depth), self.start, true) {
+                        board = next_board;
+                    }
+                    index = child;
+                    depth += 1;
+                },
+                None => break,
+            }
+        }
+        (index, depth, board)
+    }
+
+    fn expand(&mut self, index: usize, depth: usize, board: &Board) {
+        let color = self.color_at(depth);
+        let plays = if self.is_or(depth) {
+            board.get_plays(&color)
+        } else {
+            (0..19).flat_map(|x| (0..19).map(move |y| (x, y)))
+                   .filter(|&(x, y)| board.state[x][y] == Square::Empty)
+                   .collect()
+        };
+        for pos in plays {
+            if let Move::Legal(child, _, _, _) = board.play_at(Some(pos), &color, self.start, true) {
+                let (proof, disproof) = self.evaluate(&child);
+                let child_index = self.nodes.len();
+                self.nodes.push(Node { pos: Some(pos), parent: Some(index), children: vec![], proof: proof, disproof: disproof, expanded: false });
+                self.nodes[index].children.push(child_index);
+            }
+        }
+        self.nodes[index].expanded = true;
+    }
+
+    fn update(&mut self, mut index: usize, mut depth: usize) {
+        loop {
+            let (proof, disproof) = {
+                let children = &self.nodes[index].children;
+                if children.is_empty() {
+                    // No move left for the side to play
+                    if self.is_or(depth) { (INFINITY, 0) } else { (0, INFINITY) }
+                }
+                else if self.is_or(depth) {
+                    (children.iter().map(|&c| self.nodes[c].proof).min().unwrap(),
+                     children.iter().fold(0, |acc, &c| add(acc, self.nodes[c].disproof)))
+                }
+                else {
+                    (children.iter().fold(0, |acc, &c| add(acc, self.nodes[c].proof)),
+                     children.iter().map(|&c| self.nodes[c].disproof).min().unwrap())
+                }
+            };
+            self.nodes[index].proof = proof;
+            self.nodes[index].disproof = disproof;
+            match self.nodes[index].parent {
+                Some(parent) => { index = parent; depth -= 1; },
+                None => break,
+            }
+        }
+    }
+
+    pub fn run(&mut self, max_nodes: usize) -> Outcome {
+        while self.nodes[0].proof != 0 && self.nodes[0].disproof != 0 && self.nodes.len() < max_nodes {
+            let (index, depth, board) = self.select();
+            self.expand(index, depth, &board);
+            self.update(index, depth);
+        }
+        if self.nodes[0].proof == 0 {
+            Outcome::Win
+        } else if self.nodes[0].disproof == 0 {
+            Outcome::Loss
+        } else {
+            Outcome::Unknown
+        }
+    }
+
+    // Follows proven children when there are some, the most-proving ones otherwise.
+    pub fn main_line(&self) -> Vec<(usize, usize)> {
+        let mut line = Vec::new();
+        let mut index = 0;
+        let mut depth = 0;
+        while !self.nodes[index].children.is_empty() {
+            let children = &self.nodes[index].children;
+            let next = if self.is_or(depth) {
+                *children.iter().min_by_key(|&&c| self.nodes[c].proof).unwrap()
+            } else if self.nodes[index].proof == 0 {
+                // Every defense is refuted, follow the one lasting the longest
+                *children.iter().max_by_key(|&&c| self.subtree_size(c)).unwrap()
+            } else {
+                *children.iter().min_by_key(|&&c| self.nodes[c].disproof).unwrap()
+            };
+            line.push(self.nodes[next].pos.unwrap());
+            index = next;
+            depth += 1;
+        }
+        line
+    }
+
+    fn subtree_size(&self, index: usize) -> usize {
+        self.nodes[index].children.iter().fold(1, |acc, &c| acc + self.subtree_size(c))
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+// Tries to prove that the position is won or lost for `color`, who is to
+// move, within `max_nodes` nodes for each side.
+pub fn prove(board: &Board, color: &Square, max_nodes: usize) -> Proof {
+    let mut search = ProofSearch::new(board, color, color);
+    let result = search.run(max_nodes);
+    if result == Outcome::Win {
+        return Proof { result: Outcome::Win, main_line: search.main_line(), nodes: search.nodes() };
+    }
+    let mut refutation = ProofSearch::new(board, color, &color.opposite());
+    let nodes = search.nodes();
+    match refutation.run(max_nodes) {
+        Outcome::Win => Proof { result: Outcome::Loss, main_line: refutation.main_line(), nodes: nodes + refutation.nodes() },
+        _ => Proof { result: Outcome::Unknown, main_line: search.main_line(), nodes: nodes + refutation.nodes() },
+    }
+}
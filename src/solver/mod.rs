@@ -0,0 +1,191 @@
+extern crate itertools;
+extern crate time;
+
+use board::{Board, BoardState, Move, Square};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use self::itertools::Itertools;
+use self::time::PreciseTime;
+
+// Maximum number of attacking moves in a forced sequence.
+pub const VCF_DEPTH: usize = 10;
+pub const VCT_DEPTH: usize = 4;
+// Maximum number of positions visited by a single solve.
+pub const MAX_NODES: usize = 20000;
+// Budget of the solve run before each search of the AI.
+pub const ROOT_NODES: usize = 300;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Threat
+{
+    // Victory by continuous fours
+    Vcf,
+    // Victory by continuous threats, fours and open threes
+    Vct,
+}
+
+pub struct Solver
+{
+    threat: Threat,
+    max_depth: usize,
+    max_nodes: usize,
+    pub nodes: usize,
+    failed: HashMap<u64, usize>,
+    start: PreciseTime,
+    // Set from another thread to stop the solve, finding nothing
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl Solver
+{
+    pub fn new(threat: Threat, max_depth: usize, max_nodes: usize) -> Solver {
+        Solver {
+            threat: threat,
+            max_depth: max_depth,
+            max_nodes: max_nodes,
+            nodes: 0,
+            failed: HashMap::new(),
+            start: PreciseTime::now(),
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // Searches a forced win for `attacker`, who is to move on `board`.
+    // The returned sequence alternates attacking moves and defensive replies,
+    // following the most resilient defense, and ends on the winning move.
+    pub fn solve(&mut self, board: &Board, attacker: &Square) -> Option<Vec<(usize, usize)>> {
+        let depth = self.max_depth;
+        self.attack(board, attacker, depth)
+    }
+
+    fn play(&self, board: &Board, pos: (usize, usize), color: &Square) -> Option<Board> {
+        match board.play_at(Some(pos), color, self.start, true) {
+            Move::Legal(child, _, _, _) => Some(child),
+            _ => None,
+        }
+    }
+
+    fn attack(&mut self, board: &Board, attacker: &Square, depth: usize) -> Option<Vec<(usize, usize)>> {
+        let defender = attacker.opposite();
+        self.nodes += 1;
+        if depth == 0 || self.nodes > self.max_nodes || self.cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        if self.failed.get(&board.hash).map_or(false, |&d| d >= depth) {
+            return None;
+        }
+        if let BoardState::FiveAligned(ref color, _) = board.game_state {
+            if *color == defender {
+                return None;
+            }
+        }
+
+        // Immediate wins, by alignment or by capture
+        let fives = board.check_five_squares(attacker);
+        for &pos in fives.iter().chain(board.check_capture_squares(attacker).iter()) {
+            if let Some(child) = self.play(board, pos, attacker) {
+                if child.game_state == BoardState::Victory(attacker.clone()) {
+                    return Some(vec![pos]);
+                }
+            }
+        }
+
+        // Every attacking move must also stop a pending five of the defender,
+        // unless it aligns five itself and forces a capture
+        let blocks = board.check_five_squares(&defender);
+        let mut candidates = fives.clone();
+        candidates.extend(board.check_four_squares(attacker));
+        if self.threat == Threat::Vct {
+            candidates.extend(board.check_three_squares(attacker));
+        }
+        let candidates = candidates.into_iter().unique()
+            .filter(|pos| blocks.is_empty() || fives.contains(pos) || (blocks.len() == 1 && blocks[0] == *pos))
+            .collect::<Vec<_>>();
+
+        for pos in candidates {
+            if let Some(child) = self.play(board, pos, attacker) {
+                if child.game_state == BoardState::Victory(attacker.clone()) {
+                    return Some(vec![pos]);
+                }
+                if let Some(line) = self.defend(&child, pos, attacker, depth) {
+                    let mut sequence = vec![pos];
+                    sequence.extend(line);
+                    return Some(sequence);
+                }
+            }
+        }
+        self.failed.insert(board.hash, depth);
+        None
+    }
+
+    fn defend(&mut self, board: &Board, threat: (usize, usize), attacker: &Square, depth: usize)
+        -> Option<Vec<(usize, usize)>>
+    {
+        let defender = attacker.opposite();
+        let mut responses = board.check_capture_squares(&defender);
+        responses.extend(board.check_five_squares(&defender));
+        responses.extend(board.check_four_squares(&defender));
+        match board.game_state {
+            // Only a capture can break a five
+            BoardState::FiveAligned(_, _) => (),
+            _ => {
+                let gains = board.check_five_squares(attacker);
+                if !gains.is_empty() {
+                    responses.extend(gains);
+                }
+                else if self.threat == Threat::Vct {
+                    let defenses = board.check_three_defenses(threat, attacker);
+                    if defenses.is_empty() {
+                        return None;
+                    }
+                    responses.extend(defenses);
+                }
+                else {
+                    return None;
+                }
+            }
+        }
+
+        let mut best: Option<Vec<(usize, usize)>> = None;
+        let mut answered = false;
+        for pos in responses.into_iter().unique() {
+            if let Some(child) = self.play(board, pos, &defender) {
+                answered = true;
+                if child.game_state == BoardState::Victory(defender.clone()) {
+                    return None;
+                }
+                match self.attack(&child, attacker, depth - 1) {
+                    None => return None,
+                    Some(line) => if best.as_ref().map_or(true, |b| b.len() < line.len() + 1) {
+                        let mut sequence = vec![pos];
+                        sequence.extend(line);
+                        best = Some(sequence);
+                    },
+                }
+            }
+        }
+        // Without an answer to the threat, the defender may still win
+        // elsewhere first, which is not searched
+        if !answered {
+            return None;
+        }
+        best
+    }
+}
+
+// Looks for a victory by continuous fours, then by continuous threats.
+pub fn solve(board: &Board, color: &Square) -> Option<Vec<(usize, usize)>> {
+    solve_within(board, color, MAX_NODES, &Arc::new(AtomicBool::new(false)))
+}
+
+pub fn solve_within(board: &Board, color: &Square, max_nodes: usize, cancel: &Arc<AtomicBool>) -> Option<Vec<(usize, usize)>> {
+    let solver = |threat, max_depth| {
+        let mut solver = Solver::new(threat, max_depth, max_nodes);
+        solver.cancel = cancel.clone();
+        solver
+    };
+    solver(Threat::Vcf, VCF_DEPTH).solve(board, color)
+        .or_else(|| solver(Threat::Vct, VCT_DEPTH).solve(board, color))
+}
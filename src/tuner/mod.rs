@@ -0,0 +1,249 @@
+extern crate rand;
+extern crate time;
+
+use board::{Board, BoardState, Move, Square, EVAL_PATTERNS};
+use eval::{Evaluator, PatternEvaluator};
+use minimax::{minimax, SearchContext};
+
+use std::i32;
+use std::i64;
+use std::collections::HashMap;
+use self::rand::Rng;
+use self::time::PreciseTime;
+
+// A position, the player to move and the result of the game for that
+// player: 1 for a win, 0 for a loss and 0.5 for a draw.
+#[derive(Clone, Debug)]
+pub struct Sample
+{
+    pub board: Board,
+    pub to_move: Square,
+    pub result: f64,
+}
+
+impl Sample
+{
+    // Parses `<361 squares> <B|W to move> <black captures> <white captures> <result>`,
+    // squares being listed row by row as 'B', 'W' or '_'.
+    pub fn parse(line: &str) -> Result<Sample, String> {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 || fields[0].len() != 361 {
+            return Err(format!("expected 5 fields and 361 squares: `{}`", line));
+        }
+        let rows = (0..19).map(|i| &fields[0][i * 19..(i + 1) * 19]).collect::<Vec<_>>().join("\n");
+        let mut board = Board::from(rows.as_str());
+        board.b_capture = fields[2].parse().map_err(|_| format!("invalid black captures `{}`", fields[2]))?;
+        board.w_capture = fields[3].parse().map_err(|_| format!("invalid white captures `{}`", fields[3]))?;
+        let to_move = match fields[1] {
+            "B" => Square::Black,
+            "W" => Square::White,
+            s => return Err(format!("invalid player `{}`", s)),
+        };
+        let result = match fields[4].parse::<f64>() {
+            Ok(result) if result >= 0.0 && result <= 1.0 => result,
+            _ => return Err(format!("invalid result `{}`", fields[4])),
+        };
+        Ok(Sample { board: board, to_move: to_move, result: result })
+    }
+
+    pub fn to_line(&self) -> String {
+        let squares = self.board.state.iter()
+            .map(|line| line.iter().map(|square| match *square {
+                Square::Empty => '_',
+                ref color => color.to_char(),
+            }).collect::<String>())
+            .collect::<String>();
+        format!("{} {} {} {} {}", squares, self.to_move.to_str(), self.board.b_capture, self.board.w_capture, self.result)
+    }
+}
+
+pub fn parse_samples(data: &str) -> Result<Vec<Sample>, String> {
+    data.lines().enumerate()
+        .filter(|&(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| Sample::parse(line).map_err(|e| format!("line {}: {}", n + 1, e)))
+        .collect()
+}
+
+// Merges the samples of the same position, symmetric ones included, into one
+// with the mean of their results.
+pub fn dedup(samples: Vec<Sample>) -> Vec<Sample> {
+    let mut merged: Vec<(Sample, usize)> = Vec::new();
+    let mut index = HashMap::new();
+    for sample in samples {
+        let key = (sample.board.canonical_hash(), sample.to_move.to_char(), sample.board.b_capture, sample.board.w_capture);
+        let found: Option<usize> = index.get(&key).cloned();
+        match found {
+            Some(i) => {
+                merged[i].0.result += sample.result;
+                merged[i].1 += 1;
+            },
+            None => {
+                index.insert(key, merged.len());
+                merged.push((sample, 1));
+            },
+        }
+    }
+    merged.into_iter().map(|(mut sample, count)| {
+        sample.result /= count as f64;
+        sample
+    }).collect()
+}
+
+// Game length after which self-play stops and counts the game as a draw.
+const MAX_PLIES: usize = 200;
+
+// Plays `games` games of the AI against itself, with a few random opening
+// moves and searches to `depth` without a time limit, and records every
+// position. Games ending on a move the engine cannot play are dropped, as
+// their result is unknown.
+pub fn self_play(games: usize, depth: usize, evaluator: &Evaluator) -> Vec<Sample> {
+    let mut rng = rand::thread_rng();
+    let mut samples = Vec::new();
+    for _ in 0..games {
+        let mut board = Board::new();
+        let mut color = Square::Black;
+        let mut positions = Vec::new();
+        // Transposition scores are from the side of the player searching
+        let mut contexts = [SearchContext::new(PreciseTime::now(), i64::MAX, HashMap::new()),
+                            SearchContext::new(PreciseTime::now(), i64::MAX, HashMap::new())];
+        let openings = rng.gen_range(2, 7);
+        // The winner once the game is over, `None` for a draw
+        let mut result = None;
+        for ply in 0..MAX_PLIES {
+            let pos = if ply < openings {
+                let plays = board.get_plays(&color).into_iter()
+                    .filter(|&pos| match board.play_at(Some(pos), &color, PreciseTime::now(), false) {
+                        Move::Legal(_, _, _, _) => true,
+                        _ => false,
+                    })
+                    .collect::<Vec<_>>();
+                if plays.is_empty() { None } else { Some(plays[rng.gen_range(0, plays.len())]) }
+            } else {
+                let ctx = &mut contexts[if color == Square::Black { 0 } else { 1 }];
+                minimax(&board, depth, i32::MIN, i32::MAX, true, None, &color, ctx, evaluator).pos
+            };
+            match board.play_at(pos, &color, PreciseTime::now(), true) {
+                Move::Legal(child, _, _, _) => board = child,
+                _ => break,
+            }
+            color = color.opposite();
+            match board.game_state {
+                BoardState::Victory(ref winner) => {
+                    result = Some(Some(winner.clone()));
+                    break;
+                },
+                BoardState::Draw => {
+                    result = Some(None);
+                    break;
+                },
+                _ => (),
+            }
+            positions.push((board.clone(), color.clone()));
+            if ply + 1 == MAX_PLIES {
+                result = Some(None);
+            }
+        }
+        let winner = match result {
+            Some(winner) => winner,
+            None => continue,
+        };
+        samples.extend(positions.into_iter().map(|(board, to_move)| Sample {
+            result: match winner {
+                Some(ref winner) if *winner == to_move => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            },
+            board: board,
+            to_move: to_move,
+        }));
+    }
+    samples
+}
+
+fn sigmoid(k: f64, value: f64) -> f64 {
+    1.0 / (1.0 + (-k * value).exp())
+}
+
+// Mean squared error between the results and the evaluation mapped to a
+// winning probability.
+pub fn error(samples: &[Sample], evaluator: &Evaluator, k: f64) -> f64 {
+    samples.iter().fold(0.0, |acc, sample| {
+        let value = evaluator.evaluate(&sample.board, &sample.to_move, &sample.to_move) as f64;
+        acc + (sample.result - sigmoid(k, value)).powi(2)
+    }) / samples.len() as f64
+}
+
+// The evaluation is linear in the pattern weights: for the player to move it
+// is the sum of `weight * (own count - waiting_factor * opponent count)`,
+// plus the capture term which is not tuned.
+struct Features
+{
+    counts: Vec<f64>,
+    offset: f64,
+    result: f64,
+}
+
+fn features(sample: &Sample, evaluator: &PatternEvaluator) -> Features {
+    let weights = &evaluator.weights;
+    let opponent = sample.to_move.opposite();
+    let counts = (0..EVAL_PATTERNS.len()).map(|i|
+        sample.board.patterns.count(&sample.to_move, i) as f64
+        - weights.waiting_factor * sample.board.patterns.count(&opponent, i) as f64)
+        .collect::<Vec<f64>>();
+    let patterns = counts.iter().zip(weights.patterns.iter()).fold(0.0, |acc, (c, &w)| acc + c * w as f64);
+    let total = evaluator.evaluate(&sample.board, &sample.to_move, &sample.to_move) as f64;
+    Features { counts: counts, offset: total - patterns, result: sample.result }
+}
+
+fn linear_error(features: &[Features], weights: &[i32], k: f64) -> f64 {
+    features.iter().fold(0.0, |acc, f| {
+        let value = f.counts.iter().zip(weights.iter()).fold(f.offset, |acc, (c, &w)| acc + c * w as f64);
+        acc + (f.result - sigmoid(k, value)).powi(2)
+    }) / features.len() as f64
+}
+
+// Scaling constant of the sigmoid fitting the current weights best.
+pub fn fit_scale(samples: &[Sample], evaluator: &Evaluator) -> f64 {
+    (0..60).map(|i| 10f64.powf(-6.0 + i as f64 / 10.0))
+        .map(|k| (k, error(samples, evaluator, k)))
+        .fold((1.0, ::std::f64::MAX), |best, (k, e)| if e < best.1 { (k, e) } else { best })
+        .0
+}
+
+// Texel tuning: local search on every pattern weight, keeping a change as
+// long as it lowers the error, with a step halved when no weight improves.
+// The five-in-a-row weight is left as is, since it marks a finished game.
+// `progress` is given the number, error and step of each iteration.
+pub fn tune(samples: &[Sample], evaluator: &PatternEvaluator, iterations: usize, progress: &Fn(usize, f64, i32)) -> PatternEvaluator {
+    let k = fit_scale(samples, evaluator);
+    let features = samples.iter().map(|s| features(s, evaluator)).collect::<Vec<_>>();
+    let mut weights = evaluator.weights.patterns;
+    let mut best = linear_error(&features, &weights, k);
+    let mut step = 64;
+    for iteration in 0..iterations {
+        let mut improved = false;
+        for i in 1..weights.len() {
+            for &delta in &[step, -step] {
+                let mut candidate = weights;
+                candidate[i] += delta;
+                let e = linear_error(&features, &candidate, k);
+                if e < best {
+                    best = e;
+                    weights = candidate;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        progress(iteration + 1, best, step);
+        if !improved {
+            if step == 1 {
+                break;
+            }
+            step /= 2;
+        }
+    }
+    let mut tuned = evaluator.clone();
+    tuned.weights.patterns = weights;
+    tuned
+}